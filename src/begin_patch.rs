@@ -0,0 +1,204 @@
+use crate::diff::{FileDiff, Hunk, Line, Patch};
+use crate::parser::{ParseError, strip_markdown_fences};
+
+const BEGIN_PATCH: &str = "*** Begin Patch";
+const END_PATCH: &str = "*** End Patch";
+const END_OF_FILE: &str = "*** End of File";
+const ADD_FILE: &str = "*** Add File: ";
+const DELETE_FILE: &str = "*** Delete File: ";
+const UPDATE_FILE: &str = "*** Update File: ";
+const MOVE_TO: &str = "*** Move to: ";
+
+pub(crate) fn is_begin_patch(line: &str) -> bool {
+    line.trim() == BEGIN_PATCH
+}
+
+enum Section {
+    None,
+    Add,
+    Delete,
+    Update,
+}
+
+fn error(line_number: usize, line: &str, message: &str) -> ParseError {
+    ParseError {
+        line_number,
+        line_content: line.to_string(),
+        message: message.to_string(),
+    }
+}
+
+/// Parses the `*** Begin Patch` / `*** End Patch` envelope emitted by
+/// OpenAI-style agents into a [`Patch`].
+///
+/// `Add File` sections become creations, `Delete File` sections deletions and
+/// `Update File` sections regular hunks, renamed when followed by `Move to`.
+/// The text after each `@@` is kept as a section hint for the matcher.
+pub fn parse_begin_patch(input: &str) -> Result<Patch, ParseError> {
+    let mut patch = Patch::default();
+    let mut current: Option<FileDiff> = None;
+    let mut section = Section::None;
+    let mut inside = false;
+    // Consecutive `@@` lines stack up into the hints of the next hunk.
+    let mut pending_hints: Vec<String> = Vec::new();
+
+    let lines = strip_markdown_fences(input);
+    for (idx, line) in lines.into_iter().enumerate() {
+        let line_number = idx + 1;
+        let trimmed = line.trim_end();
+
+        if !inside {
+            if is_begin_patch(trimmed) {
+                inside = true;
+            }
+            continue;
+        }
+
+        if trimmed == END_PATCH {
+            inside = false;
+            continue;
+        }
+
+        if let Some(path) = trimmed.strip_prefix(ADD_FILE) {
+            patch.diffs.extend(current.take());
+            current = Some(FileDiff {
+                old_file: "/dev/null".to_string(),
+                new_file: path.trim().to_string(),
+                hunks: vec![Hunk::default()],
+                ..Default::default()
+            });
+            section = Section::Add;
+            continue;
+        }
+
+        if let Some(path) = trimmed.strip_prefix(DELETE_FILE) {
+            patch.diffs.extend(current.take());
+            current = Some(FileDiff {
+                old_file: path.trim().to_string(),
+                new_file: "/dev/null".to_string(),
+                ..Default::default()
+            });
+            section = Section::Delete;
+            continue;
+        }
+
+        if let Some(path) = trimmed.strip_prefix(UPDATE_FILE) {
+            patch.diffs.extend(current.take());
+            current = Some(FileDiff {
+                old_file: path.trim().to_string(),
+                new_file: path.trim().to_string(),
+                ..Default::default()
+            });
+            section = Section::Update;
+            pending_hints.clear();
+            continue;
+        }
+
+        if let Some(path) = trimmed.strip_prefix(MOVE_TO) {
+            match (&section, current.as_mut()) {
+                (Section::Update, Some(diff)) => {
+                    diff.new_file = path.trim().to_string();
+                    diff.is_rename = diff.new_file != diff.old_file;
+                }
+                _ => {
+                    return Err(error(
+                        line_number,
+                        line,
+                        "'Move to' must follow an 'Update File' line",
+                    ));
+                }
+            }
+            continue;
+        }
+
+        if trimmed == END_OF_FILE {
+            continue;
+        }
+
+        if trimmed.starts_with("*** ") {
+            return Err(error(line_number, line, "Unknown patch directive"));
+        }
+
+        let Some(diff) = current.as_mut() else {
+            if trimmed.is_empty() {
+                continue;
+            }
+            return Err(error(
+                line_number,
+                line,
+                "Patch content found before any file section",
+            ));
+        };
+
+        match section {
+            Section::Add => {
+                let text = line.strip_prefix('+').unwrap_or(line);
+                if let Some(hunk) = diff.hunks.last_mut() {
+                    hunk.lines.push(Line::Addition(text.to_string()));
+                }
+            }
+            Section::Delete => {
+                if !trimmed.is_empty() {
+                    return Err(error(
+                        line_number,
+                        line,
+                        "'Delete File' sections cannot contain lines",
+                    ));
+                }
+            }
+            Section::Update => {
+                if let Some(hint) = trimmed.strip_prefix("@@") {
+                    let starts_new_hunk =
+                        diff.hunks.last().is_none_or(|hunk| !hunk.lines.is_empty());
+                    if starts_new_hunk {
+                        pending_hints.clear();
+                    }
+                    let hint = hint.trim();
+                    if !hint.is_empty() {
+                        pending_hints.push(hint.to_string());
+                    }
+                    if starts_new_hunk {
+                        diff.hunks.push(Hunk::default());
+                    }
+                    if let Some(hunk) = diff.hunks.last_mut() {
+                        hunk.section_hints = pending_hints.clone();
+                    }
+                    continue;
+                }
+
+                if diff.hunks.is_empty() {
+                    diff.hunks.push(Hunk::default());
+                }
+                let Some(hunk) = diff.hunks.last_mut() else {
+                    continue;
+                };
+                if let Some(text) = line.strip_prefix('+') {
+                    hunk.lines.push(Line::Addition(text.to_string()));
+                } else if let Some(text) = line.strip_prefix('-') {
+                    hunk.lines.push(Line::Removal(text.to_string()));
+                } else if let Some(text) = line.strip_prefix(' ') {
+                    hunk.lines.push(Line::Context(text.to_string()));
+                } else {
+                    hunk.lines.push(Line::Context(line.to_string()));
+                }
+            }
+            Section::None => {}
+        }
+    }
+
+    patch.diffs.extend(current.take());
+
+    for diff in &mut patch.diffs {
+        diff.hunks.retain(|hunk| !hunk.lines.is_empty());
+        for hunk in &mut diff.hunks {
+            hunk.old_lines = hunk.anchor_len();
+            hunk.new_lines = hunk
+                .lines
+                .iter()
+                .filter(|l| matches!(l, Line::Context(_) | Line::Addition(_)))
+                .count();
+        }
+    }
+
+    Ok(patch)
+}
@@ -0,0 +1,116 @@
+use crate::diff::{Hunk, Line};
+use crate::patcher::{self, HunkMatch, MatchOptions, MatchStrategy};
+use lcs::LcsTable;
+
+/// How a hunk that could not be placed confidently is written into the file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ConflictStyle {
+    /// `<<<<<<<` current lines, `=======`, the hunk's result, `>>>>>>>`.
+    Merge,
+    /// Like [`ConflictStyle::Merge`], plus the lines the hunk expected
+    /// after a `|||||||` marker.
+    Diff3,
+}
+
+/// Finds where a hunk fits best, however low the score.
+///
+/// The anchor-point heuristic is tried first with no threshold. When even its
+/// anchors cannot be found, every window of the hunk's length is compared
+/// with the hunk's context and removals, the closest to the hunk's line
+/// number winning ties. Returns `None` when no line at all is shared.
+pub fn find_best_location(
+    source_lines: &[String],
+    hunk: &Hunk,
+    options: MatchOptions,
+) -> Option<HunkMatch> {
+    let (clean_source_map, clean_index_map) = patcher::build_lookup_tables(source_lines);
+    let fuzzy = patcher::find_fuzzy_match(
+        source_lines,
+        &clean_source_map,
+        &clean_index_map,
+        hunk,
+        MatchOptions {
+            fuzziness: 2,
+            match_threshold: 0.0,
+            ..options
+        },
+    );
+    if let Some(best) = fuzzy.into_iter().next() {
+        return Some(best);
+    }
+
+    let expected: Vec<String> = hunk
+        .lines
+        .iter()
+        .filter_map(|line| match line {
+            Line::Context(text) | Line::Removal(text) => Some(patcher::normalize_line(text)),
+            Line::Addition(_) => None,
+        })
+        .collect();
+    let expected: Vec<&str> = expected.iter().map(String::as_str).collect();
+    let window = expected.len();
+    if window == 0 || source_lines.len() < options.min_line + window {
+        return None;
+    }
+    let normalized: Vec<String> = source_lines
+        .iter()
+        .map(|l| patcher::normalize_line(l))
+        .collect();
+    let target = hunk.old_start.saturating_sub(1);
+
+    (options.min_line..=source_lines.len() - window)
+        .map(|start| {
+            let candidate: Vec<&str> = normalized[start..start + window]
+                .iter()
+                .map(String::as_str)
+                .collect();
+            let shared = LcsTable::new(&expected, &candidate)
+                .longest_common_subsequence()
+                .len();
+            (start, shared)
+        })
+        .filter(|(_, shared)| *shared > 0)
+        .max_by_key(|(start, shared)| (*shared, std::cmp::Reverse(start.abs_diff(target))))
+        .map(|(start, shared)| HunkMatch {
+            start_index: start,
+            matched_length: window,
+            score: shared as f32 / window as f32,
+            density: 1.0,
+            strategy: MatchStrategy::Fuzzy,
+        })
+}
+
+/// Replaces `source_lines[start..start + matched_length]` with a git-style
+/// conflict block opposing those lines to the result of `hunk`. Returns the
+/// number of lines the block takes.
+pub fn insert_conflict(
+    source_lines: &mut Vec<String>,
+    hunk: &Hunk,
+    start: usize,
+    matched_length: usize,
+    style: ConflictStyle,
+    label: &str,
+) -> usize {
+    let start = start.min(source_lines.len());
+    let end = (start + matched_length).min(source_lines.len());
+
+    let mut block = vec!["<<<<<<< current".to_string()];
+    block.extend(source_lines[start..end].iter().cloned());
+    if style == ConflictStyle::Diff3 {
+        block.push(format!("||||||| expected by {label}"));
+        block.extend(hunk.lines.iter().filter_map(|line| match line {
+            Line::Context(text) | Line::Removal(text) => Some(text.clone()),
+            Line::Addition(_) => None,
+        }));
+    }
+    block.push("=======".to_string());
+    block.extend(hunk.lines.iter().filter_map(|line| match line {
+        Line::Context(text) | Line::Addition(text) => Some(text.clone()),
+        Line::Removal(_) => None,
+    }));
+    block.push(format!(">>>>>>> {label}"));
+
+    let block_len = block.len();
+    source_lines.splice(start..end, block);
+    block_len
+}
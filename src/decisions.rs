@@ -0,0 +1,119 @@
+use crate::diff::{Hunk, Line};
+use crate::patcher::HunkMatch;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// What was decided for a hunk at a prompt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "kebab-case")]
+pub enum Choice {
+    /// Apply the hunk at this 1-based line.
+    Apply { line: usize },
+    /// Leave the hunk out.
+    Skip,
+}
+
+/// A choice made for one hunk of one file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Decision {
+    pub file: String,
+    /// [`hunk_hash`] of the hunk.
+    pub hunk: String,
+    /// [`content_hash`] of the file when the choice was made.
+    pub source: String,
+    #[serde(flatten)]
+    pub choice: Choice,
+}
+
+/// The decisions written by `--record` and read by `--replay`.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DecisionLog {
+    pub decisions: Vec<Decision>,
+}
+
+impl DecisionLog {
+    pub fn load(path: &Path) -> io::Result<Self> {
+        serde_json::from_str(&fs::read_to_string(path)?)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let json = serde_json::to_string_pretty(self).map_err(io::Error::other)?;
+        fs::write(path, format!("{json}\n"))
+    }
+
+    /// The decision recorded for the hunk with hash `hunk` in `file`,
+    /// preferring one made on the same content (`source`) when the same
+    /// hunk appears more than once. The caller must check that the
+    /// content matches before trusting it.
+    pub fn find(&self, file: &str, hunk: &str, source: &str) -> Option<&Decision> {
+        let mut recorded = self
+            .decisions
+            .iter()
+            .filter(|d| d.file == file && d.hunk == hunk);
+        let first = recorded.clone().next();
+        recorded.find(|d| d.source == source).or(first)
+    }
+}
+
+/// What a recorded decision says to do with a hunk now.
+#[derive(Debug, Clone)]
+pub enum Replay {
+    /// Apply the hunk at the recorded line.
+    Apply(HunkMatch),
+    /// Leave the hunk out, as was chosen before.
+    Skip,
+    /// The file changed since the decision was made, so it no longer holds.
+    Stale,
+}
+
+/// Replays `decision` for a hunk that currently matches at `matches`, in a
+/// file whose [`content_hash`] is `source`. A choice to apply only holds if
+/// one of the matches starts at the recorded line.
+pub fn replay(decision: &Decision, source: &str, matches: &[HunkMatch]) -> Replay {
+    if decision.source != source {
+        return Replay::Stale;
+    }
+    match decision.choice {
+        Choice::Apply { line } => matches
+            .iter()
+            .find(|m| m.start_index + 1 == line)
+            .map_or(Replay::Stale, |m| Replay::Apply(m.clone())),
+        Choice::Skip => Replay::Skip,
+    }
+}
+
+/// 64-bit FNV-1a, written out as hex. Unlike the standard library's hasher,
+/// it gives the same value across Rust versions, so recorded files stay valid.
+fn fnv1a<'a>(parts: impl IntoIterator<Item = &'a str>) -> String {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for part in parts {
+        for byte in part.bytes().chain([b'\n']) {
+            hash ^= u64::from(byte);
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+    format!("{hash:016x}")
+}
+
+/// Hashes the lines of a hunk, not its line numbers, so the key survives
+/// edits elsewhere in the diff.
+pub fn hunk_hash(hunk: &Hunk) -> String {
+    let lines: Vec<String> = hunk
+        .lines
+        .iter()
+        .map(|line| match line {
+            Line::Context(text) => format!(" {text}"),
+            Line::Addition(text) => format!("+{text}"),
+            Line::Removal(text) => format!("-{text}"),
+        })
+        .collect();
+    fnv1a(lines.iter().map(String::as_str))
+}
+
+/// Hashes the content of a file, as lines.
+pub fn content_hash(lines: &[String]) -> String {
+    fnv1a(lines.iter().map(String::as_str))
+}
@@ -1,3 +1,9 @@
+use std::fmt;
+
+/// The line git writes after the last line of a side that does not end with
+/// a newline.
+pub const NO_NEWLINE_MARKER: &str = "\\ No newline at end of file";
+
 #[derive(Debug, Clone)]
 pub enum Line {
     Context(String),
@@ -5,16 +11,85 @@ pub enum Line {
     Removal(String),
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 pub struct Hunk {
     pub old_start: usize,
     pub old_lines: usize,
     pub new_start: usize,
     pub new_lines: usize,
     pub lines: Vec<Line>,
+    /// Lines naming the enclosing scope of the hunk (e.g. `class Foo`), from
+    /// outermost to innermost. Used to disambiguate between matches.
+    pub section_hints: Vec<String>,
+    /// Whether the last old (context or removal) line of the hunk is the end
+    /// of a file without a trailing newline.
+    pub old_missing_newline: bool,
+    /// Same for the last new (context or addition) line.
+    pub new_missing_newline: bool,
 }
 
 impl Hunk {
+    /// Number of lines the hunk expects to find in the original file, i.e.
+    /// its context and removal lines.
+    pub fn anchor_len(&self) -> usize {
+        self.lines
+            .iter()
+            .filter(|line| matches!(line, Line::Context(_) | Line::Removal(_)))
+            .count()
+    }
+
+    /// Number of lines the hunk leaves in the new file, i.e. its context and
+    /// addition lines.
+    pub fn new_len(&self) -> usize {
+        self.lines
+            .iter()
+            .filter(|line| matches!(line, Line::Context(_) | Line::Addition(_)))
+            .count()
+    }
+
+    /// Writes the hunk with the given start lines and counts recomputed from
+    /// its lines.
+    fn write_unified(
+        &self,
+        out: &mut impl fmt::Write,
+        old_start: usize,
+        new_start: usize,
+    ) -> fmt::Result {
+        write!(
+            out,
+            "@@ -{old_start},{} +{new_start},{} @@",
+            self.anchor_len(),
+            self.new_len()
+        )?;
+        if let Some(hint) = self.section_hints.last() {
+            write!(out, " {hint}")?;
+        }
+        writeln!(out)?;
+
+        let last_old = self
+            .lines
+            .iter()
+            .rposition(|line| !matches!(line, Line::Addition(_)));
+        let last_new = self
+            .lines
+            .iter()
+            .rposition(|line| !matches!(line, Line::Removal(_)));
+        for (idx, line) in self.lines.iter().enumerate() {
+            let (marker, text) = match line {
+                Line::Context(text) => (' ', text),
+                Line::Addition(text) => ('+', text),
+                Line::Removal(text) => ('-', text),
+            };
+            writeln!(out, "{marker}{text}")?;
+            if (self.old_missing_newline && last_old == Some(idx))
+                || (self.new_missing_newline && last_new == Some(idx))
+            {
+                writeln!(out, "{NO_NEWLINE_MARKER}")?;
+            }
+        }
+        Ok(())
+    }
+
     pub fn invert(&self) -> Hunk {
         let inverted_lines = self
             .lines
@@ -32,6 +107,9 @@ impl Hunk {
             new_start: self.old_start,
             new_lines: self.old_lines,
             lines: inverted_lines,
+            section_hints: self.section_hints.clone(),
+            old_missing_newline: self.new_missing_newline,
+            new_missing_newline: self.old_missing_newline,
         }
     }
 }
@@ -41,6 +119,15 @@ pub struct FileDiff {
     pub old_file: String,
     pub new_file: String,
     pub hunks: Vec<Hunk>,
+    /// Whether `old_file` should be removed once `new_file` is written.
+    pub is_rename: bool,
+    /// Abbreviated git blob ids of the old and new content, from an
+    /// `index abc123..def456` line.
+    pub old_blob: Option<String>,
+    pub new_blob: Option<String>,
+    /// File modes such as `100644`, from git's mode lines.
+    pub old_mode: Option<String>,
+    pub new_mode: Option<String>,
 }
 
 impl FileDiff {
@@ -49,6 +136,11 @@ impl FileDiff {
             old_file: self.new_file.clone(),
             new_file: self.old_file.clone(),
             hunks: self.hunks.iter().map(|h| h.invert()).collect(),
+            is_rename: self.is_rename,
+            old_blob: self.new_blob.clone(),
+            new_blob: self.old_blob.clone(),
+            old_mode: self.new_mode.clone(),
+            new_mode: self.old_mode.clone(),
         }
     }
 }
@@ -65,3 +157,150 @@ impl Patch {
         }
     }
 }
+
+/// Formats a header path the way git does: with `prefix` (`a/` or `b/`),
+/// except for `/dev/null`, and C-quoted when it holds quotes, backslashes,
+/// control or non-ASCII characters.
+fn header_path(path: &str, prefix: &str) -> String {
+    if path == "/dev/null" {
+        return path.to_string();
+    }
+    let full = format!("{prefix}{path}");
+    if !full
+        .chars()
+        .any(|c| c == '"' || c == '\\' || c.is_control() || !c.is_ascii())
+    {
+        return full;
+    }
+    let mut quoted = String::from("\"");
+    for byte in full.bytes() {
+        match byte {
+            b'"' => quoted.push_str("\\\""),
+            b'\\' => quoted.push_str("\\\\"),
+            b'\t' => quoted.push_str("\\t"),
+            b'\n' => quoted.push_str("\\n"),
+            0x20..=0x7e => quoted.push(byte as char),
+            _ => quoted.push_str(&format!("\\{byte:03o}")),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Writes `hunks` as the body of a unified diff from `old_file` to
+/// `new_file`: the `---` / `+++` headers, then each hunk.
+///
+/// The hunks are written in file order. The `@@` line counts are recomputed
+/// from the hunk lines, and the new start of each hunk accounts for the
+/// hunks above it, so the result is a valid diff even when the input had
+/// wrong or missing line numbers.
+pub fn write_unified_hunks(
+    out: &mut impl fmt::Write,
+    old_file: &str,
+    new_file: &str,
+    hunks: &[Hunk],
+) -> fmt::Result {
+    writeln!(out, "--- {}", header_path(old_file, "a/"))?;
+    writeln!(out, "+++ {}", header_path(new_file, "b/"))?;
+    let mut ordered: Vec<&Hunk> = hunks.iter().collect();
+    ordered.sort_by_key(|hunk| hunk.old_start);
+    let mut delta: isize = 0;
+    for hunk in ordered {
+        let old_lines = hunk.anchor_len();
+        let new_lines = hunk.new_len();
+        // A range with lines starts at line 1 at the earliest; an empty one
+        // names the line it follows.
+        let old_start = if old_lines > 0 {
+            hunk.old_start.max(1)
+        } else {
+            hunk.old_start
+        };
+        let new_start = match (old_lines, new_lines) {
+            (0, 0) => old_start as isize,
+            (_, 0) => old_start as isize - 1,
+            (0, _) => old_start as isize + 1,
+            _ => old_start as isize,
+        } + delta;
+        delta += new_lines as isize - old_lines as isize;
+        hunk.write_unified(out, old_start, new_start.max(0) as usize)?;
+    }
+    Ok(())
+}
+
+/// The hunk alone, with its own line numbers and recomputed counts.
+impl fmt::Display for Hunk {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write_unified(f, self.old_start, self.new_start)
+    }
+}
+
+/// A `git diff` style section: the `diff --git` line, mode, rename and
+/// `index` lines, then the headers and hunks as written by
+/// [`write_unified_hunks`]. The headers are left out when there are no
+/// hunks, e.g. for a pure rename.
+impl fmt::Display for FileDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let created = self.old_file == "/dev/null";
+        let deleted = self.new_file == "/dev/null";
+        let a = if created {
+            &self.new_file
+        } else {
+            &self.old_file
+        };
+        let b = if deleted {
+            &self.old_file
+        } else {
+            &self.new_file
+        };
+        writeln!(
+            f,
+            "diff --git {} {}",
+            header_path(a, "a/"),
+            header_path(b, "b/")
+        )?;
+
+        if created {
+            writeln!(
+                f,
+                "new file mode {}",
+                self.new_mode.as_deref().unwrap_or("100644")
+            )?;
+        } else if deleted {
+            writeln!(
+                f,
+                "deleted file mode {}",
+                self.old_mode.as_deref().unwrap_or("100644")
+            )?;
+        } else if let (Some(old_mode), Some(new_mode)) = (&self.old_mode, &self.new_mode)
+            && old_mode != new_mode
+        {
+            writeln!(f, "old mode {old_mode}\nnew mode {new_mode}")?;
+        }
+        if self.is_rename {
+            writeln!(f, "rename from {}", header_path(&self.old_file, ""))?;
+            writeln!(f, "rename to {}", header_path(&self.new_file, ""))?;
+        }
+        if let (Some(old_blob), Some(new_blob)) = (&self.old_blob, &self.new_blob) {
+            write!(f, "index {old_blob}..{new_blob}")?;
+            if !created
+                && !deleted
+                && let Some(mode) = &self.old_mode
+                && self.new_mode.as_ref() == Some(mode)
+            {
+                write!(f, " {mode}")?;
+            }
+            writeln!(f)?;
+        }
+
+        if self.hunks.is_empty() {
+            return Ok(());
+        }
+        write_unified_hunks(f, &self.old_file, &self.new_file, &self.hunks)
+    }
+}
+
+impl fmt::Display for Patch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.diffs.iter().try_for_each(|diff| write!(f, "{diff}"))
+    }
+}
@@ -0,0 +1,217 @@
+use crate::diff::{Hunk, Line};
+use crate::patcher::{self, MatchOptions};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A file a diff section may have been meant for.
+#[derive(Debug, Clone)]
+pub struct Candidate {
+    pub path: PathBuf,
+    /// How well the hunks match the file, from `0.0` (none matched) to `1.0`
+    /// (all matched exactly).
+    pub score: f32,
+}
+
+/// Lowest score of a candidate picked without asking: its hunks must match
+/// exactly or up to whitespace.
+const CLEAR_WINNER_MIN_SCORE: f32 = 0.9;
+/// How far a candidate picked without asking must be ahead of the runner-up.
+const CLEAR_WINNER_MARGIN: f32 = 0.2;
+
+/// Files larger than this are never searched for hunk content.
+const MAX_SEARCHED_FILE_BYTES: u64 = 1024 * 1024;
+/// Share of the distinct context and removal lines of a diff a file must
+/// contain before its hunks are matched against it.
+const MIN_SHARED_LINE_RATIO: f32 = 0.5;
+
+const PATCH_EXTENSIONS: &[&str] = &["diff", "patch", "rej", "orig"];
+
+/// Lists the files under `root`, relative to it, skipping hidden files and
+/// whatever `.gitignore` and friends exclude.
+pub fn list_files(root: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = ignore::WalkBuilder::new(root)
+        .build()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_some_and(|t| t.is_file()))
+        .filter_map(|entry| entry.path().strip_prefix(root).ok().map(Path::to_path_buf))
+        .collect();
+    files.sort();
+    files
+}
+
+/// The name a file is known by in code: its stem, or the directory name for
+/// module entry points such as `mod.rs`, `index.js` or `__init__.py`.
+/// Lowercased, with a plural `s` dropped, so `utils.rs` and `util/mod.rs`
+/// agree.
+fn module_name(path: &Path) -> Option<String> {
+    let stem = path.file_stem()?.to_str()?;
+    let name = if matches!(stem, "mod" | "index" | "__init__") {
+        path.parent()?.file_name()?.to_str()?
+    } else {
+        stem
+    };
+    let name = name.to_lowercase();
+    Some(name.strip_suffix('s').unwrap_or(&name).to_string())
+}
+
+/// Number of trailing path components `a` and `b` share.
+fn common_suffix_len(a: &Path, b: &Path) -> usize {
+    a.components()
+        .rev()
+        .zip(b.components().rev())
+        .take_while(|(x, y)| x == y)
+        .count()
+}
+
+/// Picks the files of `files` that could be what `wanted` refers to: the
+/// same file name, or the same module name and extension.
+pub fn similar_paths(files: &[PathBuf], wanted: &str) -> Vec<PathBuf> {
+    let wanted = Path::new(wanted);
+    let wanted_name = module_name(wanted);
+    let mut similar: Vec<&PathBuf> = files
+        .iter()
+        .filter(|file| {
+            file.file_name() == wanted.file_name()
+                || (file.extension() == wanted.extension()
+                    && wanted_name.is_some()
+                    && module_name(file) == wanted_name)
+        })
+        .collect();
+    // Paths sharing more trailing components come first.
+    similar.sort_by_key(|file| std::cmp::Reverse(common_suffix_len(file, wanted)));
+    similar.into_iter().cloned().collect()
+}
+
+/// Scores how well `hunks` match `source_lines`: the average, over hunks that
+/// have context or removals, of the best match score (`1.0` for an exact
+/// match, `0.0` for none).
+pub fn hunk_match_score(source_lines: &[String], hunks: &[Hunk], options: MatchOptions) -> f32 {
+    let anchored: Vec<&Hunk> = hunks.iter().filter(|h| h.anchor_len() > 0).collect();
+    if anchored.is_empty() {
+        return 0.0;
+    }
+    let (clean_source_map, clean_index_map) = patcher::build_lookup_tables(source_lines);
+    let total: f32 = anchored
+        .iter()
+        .map(|hunk| {
+            if !patcher::find_strict_match(source_lines, hunk, 0, false).is_empty() {
+                return 1.0;
+            }
+            if options.fuzziness == 0 {
+                return 0.0;
+            }
+            patcher::find_fuzzy_match(
+                source_lines,
+                &clean_source_map,
+                &clean_index_map,
+                hunk,
+                MatchOptions {
+                    min_line: 0,
+                    ..options
+                },
+            )
+            .first()
+            .map_or(0.0, |m| m.score)
+        })
+        .sum();
+    total / anchored.len() as f32
+}
+
+fn read_lines(path: &Path) -> Option<Vec<String>> {
+    if fs::metadata(path).ok()?.len() > MAX_SEARCHED_FILE_BYTES {
+        return None;
+    }
+    let content = fs::read(path).ok()?;
+    if content.contains(&0) {
+        return None;
+    }
+    Some(
+        String::from_utf8_lossy(&content)
+            .lines()
+            .map(String::from)
+            .collect(),
+    )
+}
+
+/// Scores each of `files` (relative to `root`) against `hunks`, best first.
+/// Files no hunk matches are left out.
+pub fn rank_candidates(
+    root: &Path,
+    files: &[PathBuf],
+    hunks: &[Hunk],
+    options: MatchOptions,
+) -> Vec<Candidate> {
+    let mut candidates: Vec<Candidate> = files
+        .iter()
+        .filter_map(|file| {
+            let source_lines = read_lines(&root.join(file))?;
+            let score = hunk_match_score(&source_lines, hunks, options);
+            (score > 0.0).then(|| Candidate {
+                path: file.clone(),
+                score,
+            })
+        })
+        .collect();
+    // Stable, so the path ordering of `files` breaks ties.
+    candidates.sort_by(|a, b| b.score.total_cmp(&a.score));
+    candidates
+}
+
+/// The first candidate, if its hunks match almost exactly and it is well
+/// ahead of the runner-up.
+pub fn clear_winner(candidates: &[Candidate]) -> Option<&Candidate> {
+    let best = candidates.first()?;
+    let runner_up = candidates.get(1).map_or(0.0, |c| c.score);
+    (best.score >= CLEAR_WINNER_MIN_SCORE && best.score - runner_up >= CLEAR_WINNER_MARGIN)
+        .then_some(best)
+}
+
+/// Searches `files` (relative to `root`) for the ones a diff without file
+/// names was made against, best first.
+///
+/// The normalized context and removal lines of the hunks are looked up in an
+/// index of each file, as built by [`patcher::build_lookup_tables`], and only
+/// files containing most of them go through full hunk matching.
+pub fn discover_targets(
+    root: &Path,
+    files: &[PathBuf],
+    hunks: &[Hunk],
+    options: MatchOptions,
+) -> Vec<Candidate> {
+    let mut wanted: Vec<String> = hunks
+        .iter()
+        .flat_map(|h| &h.lines)
+        .filter_map(|line| match line {
+            Line::Context(text) | Line::Removal(text) => Some(patcher::normalize_line(text)),
+            Line::Addition(_) => None,
+        })
+        .filter(|norm| !norm.is_empty())
+        .collect();
+    wanted.sort();
+    wanted.dedup();
+    if wanted.is_empty() {
+        return Vec::new();
+    }
+
+    let plausible: Vec<PathBuf> = files
+        .iter()
+        .filter(|file| {
+            // Patch files hold copies of the hunks themselves.
+            let extension = file.extension().and_then(|e| e.to_str()).unwrap_or("");
+            if PATCH_EXTENSIONS.contains(&extension) {
+                return false;
+            }
+            let Some(source_lines) = read_lines(&root.join(file)) else {
+                return false;
+            };
+            let (_, clean_index_map) = patcher::build_lookup_tables(&source_lines);
+            let shared = wanted
+                .iter()
+                .filter(|norm| clean_index_map.contains_key(*norm))
+                .count();
+            shared as f32 >= wanted.len() as f32 * MIN_SHARED_LINE_RATIO
+        })
+        .cloned()
+        .collect();
+    rank_candidates(root, &plausible, hunks, options)
+}
@@ -0,0 +1,154 @@
+use crate::diff::{Hunk, Line};
+use crate::patcher::{self, HunkMatch, MatchOptions};
+use regex::Regex;
+
+/// Patterns recognizing the "the rest is unchanged" comments LLMs put in place
+/// of code they did not want to repeat. A bare `...` is not enough, as it is
+/// also code (a Python stub body, a Rust range pattern).
+pub const DEFAULT_ELISION_PATTERNS: &[&str] = &[
+    // `// ...`, `# ... existing code ...`, `/* ... */`, `<!-- ... -->`
+    r"^\s*(?://+|#+|--|;+|/\*+|<!--|\*)\s*(?:\.\.\.|…)(?:\s[^\n]*)?$",
+    // `... existing code ...` without a comment prefix
+    r"(?i)^\s*(?:\.\.\.|…)\s*(?:existing code|rest of (?:the )?(?:file|code|function|class|method|module|implementation)|(?:code |lines? )?unchanged|remaining code)\b[^\n]*$",
+    // `// existing code`, `# rest of the file unchanged`, `-- remaining code`
+    r"(?i)^\s*(?://+|#+|--|;+|/\*+|<!--)\s*(?:\.\.\.\s*)?(?:existing code|rest of (?:the )?(?:file|code|function|class|method|module|implementation)|(?:code |lines? )?unchanged|remaining code)\b[^\n]*$",
+];
+
+/// Decides whether a line is an elision marker.
+#[derive(Debug, Clone)]
+pub struct ElisionMatcher {
+    patterns: Vec<Regex>,
+}
+
+impl Default for ElisionMatcher {
+    fn default() -> Self {
+        ElisionMatcher::new(&[]).expect("Invalid default elision pattern")
+    }
+}
+
+impl ElisionMatcher {
+    /// Builds a matcher from the default patterns plus `extra_patterns`.
+    pub fn new(extra_patterns: &[String]) -> Result<Self, regex::Error> {
+        let mut patterns = DEFAULT_ELISION_PATTERNS
+            .iter()
+            .map(|p| Regex::new(p))
+            .collect::<Result<Vec<_>, _>>()?;
+        for pattern in extra_patterns {
+            patterns.push(Regex::new(pattern)?);
+        }
+        Ok(ElisionMatcher { patterns })
+    }
+
+    /// A matcher that recognizes nothing, for when elision handling is off.
+    pub fn disabled() -> Self {
+        ElisionMatcher {
+            patterns: Vec::new(),
+        }
+    }
+
+    pub fn is_marker(&self, line: &str) -> bool {
+        !line.trim().is_empty() && self.patterns.iter().any(|re| re.is_match(line))
+    }
+
+    /// Whether any context line of `hunk` is an elision marker.
+    pub fn has_markers(&self, hunk: &Hunk) -> bool {
+        hunk.lines
+            .iter()
+            .any(|line| matches!(line, Line::Context(text) if self.is_marker(text)))
+    }
+
+    /// Replaces the elision markers in the context of `hunk` with the lines
+    /// they stand for in `source_lines`.
+    ///
+    /// The hunk is split at each marker and the segments are located one
+    /// after the other; whatever lies between two consecutive segments fills
+    /// the gap. Segments made only of additions stick to the previous segment.
+    /// Returns `None` when a segment cannot be located.
+    pub fn expand_elisions(
+        &self,
+        source_lines: &[String],
+        hunk: &Hunk,
+        options: MatchOptions,
+    ) -> Option<Hunk> {
+        let mut segments: Vec<Hunk> = vec![Hunk::default()];
+        for line in &hunk.lines {
+            match line {
+                Line::Context(text) if self.is_marker(text) => segments.push(Hunk::default()),
+                other => segments.last_mut()?.lines.push(other.clone()),
+            }
+        }
+        segments.retain(|s| !s.lines.is_empty());
+
+        let (clean_source_map, clean_index_map) = patcher::build_lookup_tables(source_lines);
+        let mut expanded = Hunk {
+            old_start: hunk.old_start,
+            new_start: hunk.new_start,
+            section_hints: hunk.section_hints.clone(),
+            old_missing_newline: hunk.old_missing_newline,
+            new_missing_newline: hunk.new_missing_newline,
+            ..Default::default()
+        };
+        // End of the previously located segment, once one has been found.
+        let mut previous_end: Option<usize> = None;
+        let mut min_line = options.min_line;
+
+        for segment in &segments {
+            if segment.anchor_len() == 0 {
+                expanded.lines.extend(segment.lines.iter().cloned());
+                continue;
+            }
+            let found = locate_segment(
+                source_lines,
+                &clean_source_map,
+                &clean_index_map,
+                segment,
+                MatchOptions {
+                    min_line,
+                    ..options
+                },
+            )?;
+            if let Some(end) = previous_end {
+                for text in &source_lines[end..found.start_index] {
+                    expanded.lines.push(Line::Context(text.clone()));
+                }
+            }
+            expanded.lines.extend(segment.lines.iter().cloned());
+            previous_end = Some(found.start_index + found.matched_length);
+            min_line = found.start_index + found.matched_length;
+        }
+
+        expanded.old_lines = expanded.anchor_len();
+        expanded.new_lines = expanded.lines.len()
+            - expanded
+                .lines
+                .iter()
+                .filter(|l| matches!(l, Line::Removal(_)))
+                .count();
+        Some(expanded)
+    }
+}
+
+fn locate_segment(
+    source_lines: &[String],
+    clean_source_map: &[(usize, String)],
+    clean_index_map: &std::collections::HashMap<String, Vec<usize>>,
+    segment: &Hunk,
+    options: MatchOptions,
+) -> Option<HunkMatch> {
+    let strict = patcher::find_strict_match(source_lines, segment, options.min_line, false);
+    if let Some(m) = strict.into_iter().next() {
+        return Some(m);
+    }
+    if options.fuzziness == 0 {
+        return None;
+    }
+    patcher::find_fuzzy_match(
+        source_lines,
+        clean_source_map,
+        clean_index_map,
+        segment,
+        options,
+    )
+    .into_iter()
+    .next()
+}
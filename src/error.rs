@@ -3,6 +3,25 @@ use crate::patcher::PatchError;
 use std::error::Error;
 use std::io;
 
+/// Every hunk was applied.
+pub const EXIT_SUCCESS: i32 = 0;
+/// A hunk could not be applied, or the run was aborted.
+pub const EXIT_FAILED: i32 = 1;
+/// The arguments or the input diff cannot be used. Also returned by the
+/// argument parser for usage errors.
+pub const EXIT_INVALID_INPUT: i32 = 2;
+/// A hunk or the target file matched several places.
+pub const EXIT_AMBIGUOUS: i32 = 3;
+/// Reading or writing a file failed.
+pub const EXIT_IO: i32 = 4;
+/// The run finished, but some files or hunks were skipped or rejected, or
+/// hunks were found already applied.
+pub const EXIT_PARTIAL: i32 = 5;
+/// Some hunks were left as conflict markers.
+pub const EXIT_CONFLICTS: i32 = 6;
+/// The run finished with warnings, and `--fail-on-warning` was given.
+pub const EXIT_WARNINGS: i32 = 7;
+
 #[derive(Debug)]
 pub enum AppError {
     Io(io::Error),
@@ -11,7 +30,32 @@ pub enum AppError {
     Clipboard(String),
     EmptyDiff,
     NoInput,
-    NoMatchingChanges { target_file: String },
+    NoMatchingChanges {
+        target_file: String,
+    },
+    TargetRequired {
+        input_kind: String,
+    },
+    InvalidPattern(String),
+    SuspiciousWholeFile {
+        path: String,
+        warnings: Vec<String>,
+    },
+    AmbiguousTarget {
+        target_file: String,
+        paths: Vec<String>,
+    },
+    UnsafePath {
+        path: String,
+        reason: String,
+    },
+    ConflictsLeft {
+        files: Vec<String>,
+    },
+    InvalidDecisionFile {
+        path: String,
+        reason: String,
+    },
 }
 
 impl std::fmt::Display for AppError {
@@ -36,6 +80,74 @@ impl std::fmt::Display for AppError {
                     "The diff contains no changes for the specified file: {target_file}"
                 )
             }
+            AppError::SuspiciousWholeFile { path, warnings } => {
+                write!(
+                    f,
+                    "Refusing to replace {path}: the code block looks incomplete."
+                )?;
+                for warning in warnings {
+                    write!(f, "\n- {warning}")?;
+                }
+                Ok(())
+            }
+            AppError::InvalidPattern(err) => write!(f, "Invalid elision pattern: {err}"),
+            AppError::TargetRequired { input_kind } => write!(
+                f,
+                "The input is {input_kind}, which carries no file names.\n\
+                 Please specify the target file: `mend <TARGET_FILE> [DIFF_FILE]`"
+            ),
+            AppError::AmbiguousTarget { target_file, paths } => {
+                write!(f, "Several files in the diff could be {target_file}:")?;
+                for path in paths {
+                    write!(f, "\n- {path}")?;
+                }
+                write!(f, "\nPlease give a longer path to the target file.")
+            }
+            AppError::ConflictsLeft { files } => {
+                write!(
+                    f,
+                    "Some hunks could not be placed and were left as conflict markers in:"
+                )?;
+                for file in files {
+                    write!(f, "\n- {file}")?;
+                }
+                Ok(())
+            }
+            AppError::InvalidDecisionFile { path, reason } => {
+                write!(
+                    f,
+                    "Could not read the recorded decisions in {path}: {reason}"
+                )
+            }
+            AppError::UnsafePath { path, reason } => write!(
+                f,
+                "Refusing to touch {path}: {reason}.\n\
+                 Use --root to choose the project root, or --unsafe-paths to allow it anyway."
+            ),
+        }
+    }
+}
+
+impl AppError {
+    /// The process exit code reporting this error.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            AppError::Io(_) | AppError::Clipboard(_) | AppError::Patch(PatchError::IOError(_)) => {
+                EXIT_IO
+            }
+            AppError::Parse(_)
+            | AppError::EmptyDiff
+            | AppError::NoInput
+            | AppError::NoMatchingChanges { .. }
+            | AppError::TargetRequired { .. }
+            | AppError::InvalidPattern(_)
+            | AppError::UnsafePath { .. }
+            | AppError::InvalidDecisionFile { .. } => EXIT_INVALID_INPUT,
+            AppError::AmbiguousTarget { .. }
+            | AppError::Patch(PatchError::AmbiguousMatch { .. }) => EXIT_AMBIGUOUS,
+            AppError::ConflictsLeft { .. } => EXIT_CONFLICTS,
+            AppError::Patch(PatchError::HunkApplicationFailed { .. })
+            | AppError::SuspiciousWholeFile { .. } => EXIT_FAILED,
         }
     }
 }
@@ -0,0 +1,221 @@
+use crate::diff::{FileDiff, Hunk, Line};
+use crate::elision::ElisionMatcher;
+use crate::parser::ParseError;
+use crate::patcher::{self, MatchOptions};
+
+/// Returns the content of the first fenced code block, or the whole input
+/// when there is none.
+pub(crate) fn extract_code_block(input: &str) -> Vec<&str> {
+    let lines: Vec<&str> = input.lines().collect();
+    let Some(start) = lines.iter().position(|l| l.trim_start().starts_with("```")) else {
+        return lines;
+    };
+    let end = lines[start + 1..]
+        .iter()
+        .position(|l| l.trim() == "```")
+        .map(|pos| start + 1 + pos)
+        .unwrap_or(lines.len());
+    lines[start + 1..end].to_vec()
+}
+
+/// A run of concrete lines between two elision markers.
+struct Segment<'a> {
+    first_line_number: usize,
+    lines: Vec<&'a str>,
+    /// Whether a marker comes before (after) the segment.
+    after_marker: bool,
+    before_marker: bool,
+    /// Whether blank lines separate the segment from the marker before it.
+    spaced: bool,
+}
+
+fn split_segments<'a>(lines: &[&'a str], elision: &ElisionMatcher) -> Vec<Segment<'a>> {
+    let mut segments = Vec::new();
+    let mut current = Segment {
+        first_line_number: 1,
+        lines: Vec::new(),
+        after_marker: false,
+        before_marker: false,
+        spaced: false,
+    };
+    for (idx, line) in lines.iter().enumerate() {
+        if elision.is_marker(line) {
+            current.before_marker = true;
+            segments.push(std::mem::replace(
+                &mut current,
+                Segment {
+                    first_line_number: idx + 2,
+                    lines: Vec::new(),
+                    after_marker: true,
+                    before_marker: false,
+                    spaced: false,
+                },
+            ));
+        } else {
+            current.lines.push(line);
+        }
+    }
+    segments.push(current);
+
+    for segment in &mut segments {
+        while segment.lines.first().is_some_and(|l| l.trim().is_empty()) {
+            segment.lines.remove(0);
+            segment.first_line_number += 1;
+            segment.spaced = true;
+        }
+        while segment.lines.last().is_some_and(|l| l.trim().is_empty()) {
+            segment.lines.pop();
+        }
+    }
+    segments.retain(|s| !s.lines.is_empty());
+    segments
+}
+
+/// Whether a line can place a segment on its own: it has more than
+/// punctuation and appears exactly once at or after `min_line`. Lines such
+/// as `}` or `else {` are everywhere and would pin a new function inside an
+/// existing one.
+fn is_distinctive(positions: &[usize], norm: &str, min_line: usize) -> bool {
+    norm.chars().any(char::is_alphanumeric)
+        && positions.iter().filter(|&&p| p >= min_line).count() == 1
+}
+
+/// Picks the occurrence of `line` at or after `min_line` closest to `target`.
+fn nearest_occurrence(positions: &[usize], min_line: usize, target: usize) -> Option<usize> {
+    positions
+        .iter()
+        .copied()
+        .filter(|&p| p >= min_line)
+        .min_by_key(|&p| (p as i64 - target as i64).unsigned_abs())
+}
+
+/// Turns a "lazy edit" snippet (a partial file where unchanged code is
+/// replaced by markers like `// ... existing code ...`) into a [`FileDiff`]
+/// against `source_lines`.
+///
+/// Each run of lines between markers is located in the file with the fuzzy
+/// matcher, using the lines that already exist in the file as anchors. The
+/// region between its first and last anchor is then diffed against the
+/// snippet. Code hidden behind markers, and code outside the located regions,
+/// is left untouched.
+///
+/// A run is only placed when one of its lines is distinctive (see
+/// [`is_distinctive`]). A run without one is appended to the file when it
+/// ends the snippet after a marker, and is an error otherwise.
+pub fn build_lazy_edit_diff(
+    snippet: &str,
+    source_lines: &[String],
+    path: &str,
+    elision: &ElisionMatcher,
+    options: MatchOptions,
+) -> Result<FileDiff, ParseError> {
+    let snippet_lines = extract_code_block(snippet);
+    let segments = split_segments(&snippet_lines, elision);
+    let (clean_source_map, clean_index_map) = patcher::build_lookup_tables(source_lines);
+
+    let mut file_diff = FileDiff {
+        old_file: path.to_string(),
+        new_file: path.to_string(),
+        ..Default::default()
+    };
+    let mut min_line = options.min_line;
+    let mut delta: isize = 0;
+
+    for segment in &segments {
+        let unanchored = || ParseError {
+            line_number: segment.first_line_number,
+            line_content: segment.lines[0].to_string(),
+            message: "Could not locate this part of the snippet in the target file".to_string(),
+        };
+
+        let known: Vec<(usize, String)> = segment
+            .lines
+            .iter()
+            .enumerate()
+            .map(|(idx, line)| (idx, patcher::normalize_line(line)))
+            .filter(|(_, norm)| !norm.is_empty() && clean_index_map.contains_key(norm))
+            .collect();
+        let anchored = known
+            .iter()
+            .any(|(_, norm)| is_distinctive(&clean_index_map[norm], norm, min_line));
+        if !anchored {
+            if !segment.after_marker || segment.before_marker {
+                return Err(ParseError {
+                    message: "This part of the snippet has no line that appears exactly once \
+                              in the target file, so its place is ambiguous"
+                        .to_string(),
+                    ..unanchored()
+                });
+            }
+            // New code after `... existing code ...` at the end of the snippet.
+            let mut lines: Vec<Line> = Vec::new();
+            if segment.spaced && source_lines.last().is_some_and(|l| !l.trim().is_empty()) {
+                lines.push(Line::Addition(String::new()));
+            }
+            lines.extend(segment.lines.iter().map(|l| Line::Addition(l.to_string())));
+            file_diff.hunks.push(Hunk {
+                old_start: source_lines.len(),
+                old_lines: 0,
+                new_start: (source_lines.len() as isize + 1 + delta) as usize,
+                new_lines: lines.len(),
+                lines,
+                ..Default::default()
+            });
+            break;
+        }
+        let (Some((_, top_key)), Some((_, bottom_key))) = (known.first(), known.last()) else {
+            return Err(unanchored());
+        };
+
+        let probe = Hunk {
+            lines: known
+                .iter()
+                .map(|(idx, _)| Line::Context(segment.lines[*idx].to_string()))
+                .collect(),
+            ..Default::default()
+        };
+        let mut matches = patcher::find_strict_match(source_lines, &probe, min_line, false);
+        if matches.is_empty() {
+            matches = patcher::find_fuzzy_match(
+                source_lines,
+                &clean_source_map,
+                &clean_index_map,
+                &probe,
+                MatchOptions {
+                    fuzziness: options.fuzziness.max(1),
+                    min_line,
+                    ..options
+                },
+            );
+        }
+        let best = matches.first().ok_or_else(unanchored)?;
+
+        let top = nearest_occurrence(&clean_index_map[top_key], min_line, best.start_index)
+            .ok_or_else(unanchored)?;
+        let bottom = nearest_occurrence(
+            &clean_index_map[bottom_key],
+            top,
+            best.start_index + best.matched_length.saturating_sub(1),
+        )
+        .ok_or_else(unanchored)?;
+
+        let region = &source_lines[top..=bottom];
+        let new_lines: Vec<String> = segment.lines.iter().map(|l| l.to_string()).collect();
+        let lines = patcher::diff_lines_by(region, &new_lines, |l| l.to_string());
+
+        if lines.iter().any(|l| !matches!(l, Line::Context(_))) {
+            file_diff.hunks.push(Hunk {
+                old_start: top + 1,
+                old_lines: region.len(),
+                new_start: (top as isize + 1 + delta) as usize,
+                new_lines: new_lines.len(),
+                lines,
+                ..Default::default()
+            });
+            delta += new_lines.len() as isize - region.len() as isize;
+        }
+        min_line = bottom + 1;
+    }
+
+    Ok(file_diff)
+}
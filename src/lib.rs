@@ -1,4 +1,19 @@
+pub mod begin_patch;
+pub mod conflict;
+pub mod decisions;
 pub mod diff;
+pub mod discovery;
+pub mod elision;
 pub mod error;
+pub mod lazy_edit;
+pub mod myers;
+pub mod normal;
 pub mod parser;
 pub mod patcher;
+pub mod paths;
+pub mod reject;
+pub mod report;
+pub mod search_replace;
+pub mod three_way;
+pub mod unified;
+pub mod whole_file;
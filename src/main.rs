@@ -1,95 +1,35 @@
-use clap::Parser;
+use clap::{CommandFactory, Parser, ValueEnum};
 use is_terminal::IsTerminal;
+use serde::Serialize;
 use std::cmp::min;
 use std::io::{self, Read};
 use std::path::{Path, PathBuf};
 
 use clipboard::{ClipboardContext, ClipboardProvider};
-use mend::error::AppError;
-
-use mend::diff::{FileDiff, Patch};
-use mend::parser;
-use mend::patcher::{self, FilePatchResult, PatchError};
+use mend::error::{self, AppError};
+
+use mend::begin_patch;
+use mend::conflict::{self, ConflictStyle};
+use mend::decisions::{self, Choice, Decision, DecisionLog, Replay};
+use mend::diff::{FileDiff, Hunk, Line, Patch};
+use mend::discovery;
+use mend::elision::ElisionMatcher;
+use mend::lazy_edit;
+use mend::normal;
+use mend::parser::{self, DiffFormat, StripLevel};
+use mend::patcher::{self, AmbiguityPolicy, FilePatchResult, MatchStrategy, PatchError};
+use mend::paths;
+use mend::reject;
+use mend::report::{FileRecord, FileStatus, HunkRecord, HunkStatus, Report, WarningCode};
+use mend::search_replace;
+use mend::three_way;
+use mend::unified;
+use mend::whole_file;
 use std::time::Instant;
 use std::{fs, process};
 
 const EXAMPLE_DIFF: &str = include_str!("../resources/example.diff");
 
-#[derive(Default, Debug)]
-struct Report {
-    files_modified: usize,
-    files_created: usize,
-    files_deleted: usize,
-    hunks_applied: usize,
-    hunks_skipped: usize,
-    warnings: Vec<String>,
-    elapsed_ms: Option<u128>,
-}
-
-impl Report {
-    fn summary(&self, dry_run: bool, revert: bool) -> String {
-        let action = if revert { "reverted" } else { "applied" };
-        let time_str = self
-            .elapsed_ms
-            .map(|ms| {
-                if ms < 1000 {
-                    format!(" in {ms}ms")
-                } else {
-                    format!(" in {:.2}s", (ms as f64) / 1000.0)
-                }
-            })
-            .unwrap_or_default();
-        let header = if dry_run {
-            format!("\nSummary{time_str}")
-        } else if self.warnings.is_empty() {
-            format!("✔ Patch {action} successfully{time_str}")
-        } else {
-            format!("✔ Patch {action} with warnings{time_str}")
-        };
-
-        let mut file_parts = Vec::new();
-        if self.files_created > 0 {
-            file_parts.push(format!("{} created", self.files_created));
-        }
-        if self.files_modified > 0 {
-            file_parts.push(format!("{} modified", self.files_modified));
-        }
-        if self.files_deleted > 0 {
-            file_parts.push(format!("{} deleted", self.files_deleted));
-        }
-
-        let mut hunk_parts = Vec::new();
-        let hunk_text = if self.hunks_applied == 1 {
-            "hunk"
-        } else {
-            "hunks"
-        };
-        hunk_parts.push(format!("{} {} {}", self.hunks_applied, hunk_text, action));
-        if self.hunks_skipped > 0 {
-            hunk_parts.push(format!("{} skipped", self.hunks_skipped));
-        }
-
-        let mut summary_parts = Vec::new();
-        if !file_parts.is_empty() {
-            summary_parts.push(file_parts.join(", "));
-        }
-        summary_parts.push(hunk_parts.join(", "));
-
-        let summary_line = summary_parts.join(" | ");
-
-        let mut final_string = format!("{header}: {summary_line}");
-
-        if !self.warnings.is_empty() {
-            final_string.push_str("\n\n--- Warnings ---");
-            for warning in &self.warnings {
-                final_string.push_str(&format!("\n- {warning}"));
-            }
-        }
-
-        final_string
-    }
-}
-
 #[derive(Parser, Debug)]
 #[command(
     author = "Tytoo",
@@ -122,47 +62,226 @@ struct Args {
     #[arg(short, long, conflicts_with = "diff_file")]
     clipboard: bool,
 
-    #[arg(short, long, default_value_t = false)]
+    #[arg(short, long, default_value_t = false, global = true)]
     revert: bool,
 
-    #[arg(long, default_value_t = false)]
+    #[arg(long, default_value_t = false, global = true)]
     ci: bool,
 
-    #[arg(long)]
+    #[arg(long, global = true)]
     confirm: bool,
 
-    #[arg(short, long)]
+    #[arg(short, long, global = true)]
     debug: bool,
 
-    #[arg(long, default_value_t = false)]
+    #[arg(long, default_value_t = false, global = true)]
     dry_run: bool,
 
+    /// Print the unified diff of the changes actually made to each file,
+    /// as the dry run does.
+    #[arg(long, global = true)]
+    emit_diff: bool,
+
     #[arg(short, long)]
     example: bool,
 
-    #[arg(short, long, default_value_t = 2)]
+    /// Treat the input as a partial file using `// ... existing code ...`
+    /// style markers for unchanged parts, and apply it to TARGET_FILE.
+    #[arg(long, conflicts_with = "revert")]
+    lazy: bool,
+
+    /// Extra regex recognizing elision markers such as `// ... unchanged ...`.
+    /// Can be given several times.
+    #[arg(long, value_name = "REGEX", global = true)]
+    elision_pattern: Vec<String>,
+
+    /// Treat elision markers as literal text.
+    #[arg(long, conflicts_with = "elision_pattern", global = true)]
+    no_elision: bool,
+
+    /// Match the hunks one by one even when the base version of a git diff
+    /// is available for a three-way merge.
+    #[arg(long, global = true)]
+    no_three_way: bool,
+
+    /// Remove N leading components from the paths in the diff headers, like
+    /// `patch -pN`. Guessed from the files on disk when omitted.
+    #[arg(short = 'p', long = "strip", value_name = "N", global = true)]
+    strip: Option<usize>,
+
+    /// Prepend DIR to every path named by the diff.
+    #[arg(long, value_name = "DIR", global = true)]
+    directory: Option<String>,
+
+    /// Directory the paths named by the diff are relative to and must stay
+    /// within. Defaults to the current directory.
+    #[arg(long, value_name = "DIR", global = true)]
+    root: Option<String>,
+
+    /// Allow the diff to name absolute paths, `..` components and files
+    /// outside the root directory.
+    #[arg(long, global = true)]
+    unsafe_paths: bool,
+
+    /// Apply the hunks that can be applied and write the others to
+    /// `<file>.rej`, instead of failing in CI mode.
+    #[arg(long, global = true)]
+    reject: bool,
+
+    /// Write hunks that cannot be placed confidently into the file as
+    /// `<<<<<<<` / `=======` / `>>>>>>>` conflict blocks.
+    #[arg(
+        long,
+        value_enum,
+        value_name = "STYLE",
+        global = true,
+        conflicts_with = "reject"
+    )]
+    conflict_style: Option<ConflictStyle>,
+
+    /// Save the choices made at hunk prompts to FILE, to be reused with
+    /// `--replay`.
+    #[arg(long, value_name = "FILE", global = true, conflicts_with = "replay")]
+    record: Option<String>,
+
+    /// Answer hunk prompts with the choices saved by `--record` in FILE.
+    /// Fails when a file changed since, so a choice no longer applies.
+    #[arg(long, value_name = "FILE", global = true)]
+    replay: Option<String>,
+
+    /// How to settle a hunk matching several places, without asking: stop
+    /// (`fail`), or take the `best` score, the `nearest` to the diff's line
+    /// number or the `first` in the file. Asks by default, or fails in CI
+    /// mode.
+    #[arg(long, value_enum, value_name = "POLICY", global = true)]
+    on_ambiguous: Option<AmbiguityPolicy>,
+
+    /// How to handle a hunk matching nowhere, without asking: stop
+    /// (`fail`), leave it out (`skip`) or save it to `<file>.rej`
+    /// (`reject`). Asks by default, or fails in CI mode.
+    #[arg(
+        long,
+        value_enum,
+        value_name = "ACTION",
+        global = true,
+        conflicts_with_all = &["reject", "conflict_style"]
+    )]
+    on_failed: Option<FailedHunkAction>,
+
+    /// Apply matches scoring at least SCORE (0.0 to 1.0) without asking.
+    /// In CI mode, matches scoring below it are discarded, so a hunk left
+    /// without any is handled as failed.
+    #[arg(long, value_name = "SCORE", value_parser = parse_score, global = true)]
+    auto_accept_score: Option<f32>,
+
+    /// What to do when the diff creates a file that already exists. Asks
+    /// by default, or fails in CI mode.
+    #[arg(long, value_enum, value_name = "ACTION", global = true)]
+    on_existing: Option<ExistingFileAction>,
+
+    #[arg(short, long, default_value_t = 2, global = true)]
     fuzziness: u8,
 
-    #[arg(short = 'm', long, default_value_t = 0.7)]
+    #[arg(short = 'm', long, default_value_t = 0.7, global = true)]
     match_threshold: f32,
 
-    #[arg(short, long)]
+    #[arg(short, long, global = true)]
     verbose: bool,
 
     #[arg(
         short,
         long,
         default_value_t = false,
+        global = true,
         conflicts_with_all = &["verbose", "debug", "confirm"]
     )]
     silent: bool,
+
+    /// Format of the final report: `text`, or `json` for tools, printed to
+    /// standard output in place of the summary.
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = ReportFormat::Text,
+        global = true,
+        conflicts_with_all = &["verbose", "debug", "emit_diff"]
+    )]
+    format: ReportFormat,
+
+    /// Exit with code 7 when the run succeeds with warnings, such as fuzzy
+    /// matches. The changes are still written.
+    #[arg(long, global = true)]
+    fail_on_warning: bool,
+
+    /// Also write the JSON report to FILE.
+    #[arg(long, value_name = "FILE", global = true)]
+    report_file: Option<String>,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum Command {
+    /// Resolve a diff against the current files and print it as a clean
+    /// unified diff, with exact headers, line numbers and counts, that
+    /// `git apply` and `patch` accept.
+    ///
+    /// Never prompts: hunks that are ambiguous or cannot be placed fail the
+    /// run, as with `--ci`, unless `--on-ambiguous` or `--on-failed` settle
+    /// them.
+    Normalize {
+        /// The diff to normalize. Read from standard input when omitted.
+        diff_file: Option<String>,
+
+        /// Write the normalized diff to FILE instead of standard output.
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<String>,
+    },
+    /// Generate a unified diff from OLD to NEW: two files, or two
+    /// directories compared file by file. `-` reads one side from standard
+    /// input.
+    Diff {
+        old: String,
+        new: String,
+
+        /// Number of unchanged lines shown around each change.
+        #[arg(
+            short = 'U',
+            long = "unified",
+            value_name = "N",
+            default_value_t = unified::DEFAULT_CONTEXT
+        )]
+        context: usize,
+
+        /// Write the diff to FILE instead of standard output.
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<String>,
+    },
+}
+
+/// Parses `--auto-accept-score`, a match score between 0 and 1.
+fn parse_score(s: &str) -> Result<f32, String> {
+    let score: f32 = s.parse().map_err(|e| format!("{e}"))?;
+    if (0.0..=1.0).contains(&score) {
+        Ok(score)
+    } else {
+        Err(format!("{s} is not between 0.0 and 1.0"))
+    }
 }
 
+/// Reads an answer to a prompt. Every prompt offers `a` to abort, which is
+/// also the answer when standard input is closed, rather than asking again
+/// forever.
 fn read_user_input() -> String {
     let mut input = String::new();
-    io::stdin()
+    let read = io::stdin()
         .read_line(&mut input)
         .expect("Failed to read line");
+    if read == 0 {
+        eprintln!("[ERROR] Standard input is closed, aborting.");
+        return "a".to_string();
+    }
     input.trim().to_string()
 }
 fn is_binary(path: &Path) -> io::Result<bool> {
@@ -208,6 +327,50 @@ fn print_match_context(
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+enum ReportFormat {
+    /// A summary for people.
+    Text,
+    /// Per-file and per-hunk records with stable warning codes.
+    Json,
+}
+
+/// The JSON report: how the run ended, around the [`Report`] itself.
+#[derive(Serialize)]
+struct JsonReport<'a> {
+    status: &'static str,
+    exit_code: i32,
+    dry_run: bool,
+    error: Option<String>,
+    #[serde(flatten)]
+    report: &'a Report,
+}
+
+/// How to handle a diff creating a file that already exists.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+enum ExistingFileAction {
+    /// Stop with an error.
+    Fail,
+    /// Replace the existing file with the new content.
+    Overwrite,
+    /// Diff the new content against the existing file and apply the result,
+    /// keeping code hidden behind elision markers.
+    Merge,
+    /// Write the new content next to the existing file, e.g. `x.new.rs`.
+    Alternate,
+}
+
+/// How to handle a hunk that matches nowhere without asking.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+enum FailedHunkAction {
+    /// Stop with an error.
+    Fail,
+    /// Leave the hunk out and apply the rest.
+    Skip,
+    /// Leave the hunk out and save it to `<file>.rej`.
+    Reject,
+}
+
 struct PatcherOptions {
     fuzziness: u8,
     debug_mode: bool,
@@ -215,6 +378,564 @@ struct PatcherOptions {
     ci: bool,
     silent: bool,
     match_threshold: f32,
+    elision: ElisionMatcher,
+    on_existing: Option<ExistingFileAction>,
+    reject: bool,
+    conflict_style: Option<ConflictStyle>,
+    on_ambiguous: Option<AmbiguityPolicy>,
+    on_failed: Option<FailedHunkAction>,
+    auto_accept_score: Option<f32>,
+    record: bool,
+    replay: Option<DecisionLog>,
+    root: Option<String>,
+    three_way: bool,
+}
+
+/// Tracks where the next hunk of a file may start and how far the actual
+/// match positions have drifted from the line numbers in the diff.
+#[derive(Default)]
+struct HunkCursor {
+    min_line: usize,
+    drift: isize,
+}
+
+impl HunkCursor {
+    fn anchorless_position(&self, hunk: &Hunk, source_len: usize) -> usize {
+        let shifted = (hunk.old_start as isize + self.drift).max(0) as usize;
+        shifted.max(self.min_line).min(source_len)
+    }
+
+    /// Where the hunk should start if it moved as much as the previous one.
+    fn expected_position(&self, hunk: &Hunk) -> usize {
+        if hunk.old_start == 0 {
+            return self.min_line;
+        }
+        (hunk.old_start as isize - 1 + self.drift).max(0) as usize
+    }
+
+    fn advance(&mut self, hunk: &Hunk, chosen_match: &patcher::HunkMatch) {
+        let new_lines_count = hunk
+            .lines
+            .iter()
+            .filter(|l| matches!(l, Line::Context(_) | Line::Addition(_)))
+            .count();
+        let end_in_source = chosen_match.start_index + new_lines_count;
+        let end_in_diff = if hunk.anchor_len() > 0 {
+            hunk.old_start.saturating_sub(1) + hunk.anchor_len()
+        } else {
+            hunk.old_start
+        };
+        self.min_line = end_in_source;
+        self.drift = end_in_source as isize - end_in_diff as isize;
+    }
+}
+
+fn apply_chosen_match(
+    source_lines: &mut Vec<String>,
+    hunk: &Hunk,
+    chosen_match: &patcher::HunkMatch,
+    cursor: &mut HunkCursor,
+    report: &mut Report,
+    hunk_index: usize,
+    file_path: &str,
+) {
+    if chosen_match.score < 0.9 {
+        report.warn(
+            WarningCode::FuzzyMatch,
+            format!(
+                "Hunk {} in '{}' was applied with a fuzzy match score ({:.2}). Please review.",
+                hunk_index + 1,
+                file_path,
+                chosen_match.score
+            ),
+        );
+    }
+
+    report.record_hunk(HunkRecord::placed(
+        hunk_index,
+        HunkStatus::Applied,
+        hunk,
+        chosen_match,
+    ));
+    *source_lines = patcher::apply_hunk(
+        source_lines,
+        hunk,
+        chosen_match.start_index,
+        chosen_match.matched_length,
+    );
+    cursor.advance(hunk, chosen_match);
+}
+
+fn find_candidate_matches(
+    source_lines: &[String],
+    hunk: &Hunk,
+    min_line: usize,
+    options: &PatcherOptions,
+) -> Vec<patcher::HunkMatch> {
+    let section_start = patcher::find_section_start(source_lines, &hunk.section_hints);
+    let mut possible_matches = section_start
+        .filter(|&start| start > min_line)
+        .map(|start| patcher::find_strict_match(source_lines, hunk, start, options.debug_mode))
+        .filter(|matches| !matches.is_empty())
+        .unwrap_or_else(|| {
+            patcher::find_strict_match(source_lines, hunk, min_line, options.debug_mode)
+        });
+
+    if possible_matches.is_empty() && options.fuzziness > 0 {
+        let (clean_source_map, clean_index_map) = patcher::build_lookup_tables(source_lines);
+
+        possible_matches = patcher::find_fuzzy_match(
+            source_lines,
+            &clean_source_map,
+            &clean_index_map,
+            hunk,
+            patcher::MatchOptions {
+                fuzziness: options.fuzziness,
+                min_line,
+                debug_mode: options.debug_mode,
+                match_threshold: options.match_threshold,
+            },
+        );
+    }
+
+    if let Some(start) = section_start {
+        possible_matches = patcher::narrow_by_section(possible_matches, start);
+    }
+    possible_matches
+}
+
+/// Fills in elision markers (` // ... unchanged ...`) found in the context of
+/// a hunk that does not match the file literally.
+fn expand_hunk_elisions(
+    source_lines: &[String],
+    hunk: &Hunk,
+    cursor: &HunkCursor,
+    options: &PatcherOptions,
+) -> Option<Hunk> {
+    if !options.elision.has_markers(hunk)
+        || !patcher::find_strict_match(source_lines, hunk, cursor.min_line, false).is_empty()
+    {
+        return None;
+    }
+    let expanded = options.elision.expand_elisions(
+        source_lines,
+        hunk,
+        patcher::MatchOptions {
+            fuzziness: options.fuzziness,
+            min_line: cursor.min_line,
+            debug_mode: options.debug_mode,
+            match_threshold: options.match_threshold,
+        },
+    );
+    if options.debug_mode {
+        println!(
+            "[DEBUG]   -> Elision markers in hunk: {}",
+            if expanded.is_some() {
+                "expanded from the file"
+            } else {
+                "could not locate every segment"
+            }
+        );
+    }
+    expanded
+}
+
+/// Number of candidate files listed when asking the user to pick one.
+const MAX_LISTED_CANDIDATES: usize = 5;
+
+fn match_options(options: &PatcherOptions) -> patcher::MatchOptions {
+    patcher::MatchOptions {
+        fuzziness: options.fuzziness,
+        min_line: 0,
+        debug_mode: false,
+        match_threshold: options.match_threshold,
+    }
+}
+
+/// Picks among candidate files for a diff section: a clear winner is taken
+/// directly, otherwise the user chooses (or, in CI, the run fails).
+fn choose_candidate(
+    description: &str,
+    candidates: &[discovery::Candidate],
+    options: &PatcherOptions,
+    report: &mut Report,
+) -> Result<String, PatchError> {
+    if let Some(winner) = discovery::clear_winner(candidates)
+        && !options.confirm
+    {
+        let path = winner.path.to_string_lossy().into_owned();
+        report.warn(
+            WarningCode::ResolvedPath,
+            format!("Resolved {description} to '{path}'"),
+        );
+        return Ok(path);
+    }
+
+    let listed = &candidates[..candidates.len().min(MAX_LISTED_CANDIDATES)];
+    let summary = listed
+        .iter()
+        .map(|c| format!("{} ({:.2})", c.path.display(), c.score))
+        .collect::<Vec<_>>()
+        .join(", ");
+    if listed.is_empty() || options.ci || options.silent {
+        let reason = if listed.is_empty() {
+            "no file in the working tree matches its hunks".to_string()
+        } else {
+            format!("several files could match: {summary}")
+        };
+        return Err(PatchError::IOError(format!(
+            "Could not resolve {description}: {reason}"
+        )));
+    }
+
+    eprintln!("[INFO] Could not find {description}. Files whose content matches the hunks:");
+    for (idx, candidate) in listed.iter().enumerate() {
+        eprintln!(
+            "  {}. {} (score {:.2})",
+            idx + 1,
+            candidate.path.display(),
+            candidate.score
+        );
+    }
+    loop {
+        eprintln!("\nEnter the index of the file to patch, or [a]bort: ");
+        let choice = read_user_input();
+        if choice.to_lowercase() == "a" {
+            return Err(PatchError::IOError(format!(
+                "User aborted: could not resolve {description}"
+            )));
+        }
+        match choice.parse::<usize>() {
+            Ok(index) if index > 0 && index <= listed.len() => {
+                return Ok(listed[index - 1].path.to_string_lossy().into_owned());
+            }
+            _ => eprintln!("Invalid choice. Please enter a valid number or 'a'."),
+        }
+    }
+}
+
+/// `path`, found by searching the root, as a path to read and write.
+fn in_root(options: &PatcherOptions, path: &str) -> String {
+    match &options.root {
+        Some(root) => paths::prepend_directory(root, path),
+        None => path.to_string(),
+    }
+}
+
+/// Looks for the file a diff meant when its header path does not exist, e.g.
+/// `src/utils.rs` for `src/util/mod.rs`, among files of the working tree
+/// with a similar name, ranked by how well the hunks match them.
+fn locate_missing_file(
+    wanted: &str,
+    file_diff: &FileDiff,
+    options: &PatcherOptions,
+    report: &mut Report,
+) -> Result<String, PatchError> {
+    let root = Path::new(options.root.as_deref().unwrap_or("."));
+    let wanted_in_root = options
+        .root
+        .as_deref()
+        .and_then(|r| Path::new(wanted).strip_prefix(r).ok())
+        .map_or(wanted.to_string(), |p| p.to_string_lossy().into_owned());
+    let files = discovery::list_files(root);
+    let similar = discovery::similar_paths(&files, &wanted_in_root);
+    let candidates =
+        discovery::rank_candidates(root, &similar, &file_diff.hunks, match_options(options));
+    choose_candidate(&format!("'{wanted}'"), &candidates, options, report)
+        .map(|path| in_root(options, &path))
+}
+
+/// Finds the file a diff without file headers was made against by searching
+/// the working tree for the content of its hunks.
+fn locate_headerless_target(
+    file_diff: &FileDiff,
+    options: &PatcherOptions,
+    report: &mut Report,
+) -> Result<String, PatchError> {
+    let root = Path::new(options.root.as_deref().unwrap_or("."));
+    let files = discovery::list_files(root);
+    let candidates =
+        discovery::discover_targets(root, &files, &file_diff.hunks, match_options(options));
+    choose_candidate(
+        "the target of a diff without file headers",
+        &candidates,
+        options,
+        report,
+    )
+    .map(|path| in_root(options, &path))
+    .map_err(|e| match e {
+        PatchError::IOError(message) => PatchError::IOError(format!(
+            "{message}. Please specify the target file: `mend <TARGET_FILE> [DIFF_FILE]`"
+        )),
+        other => other,
+    })
+}
+
+/// Checks that the lines a deletion diff removes are the content of the file,
+/// so that a stale diff cannot wipe a file it does not describe. Returns
+/// whether the file should be deleted.
+fn verify_deletion(
+    file_diff: &FileDiff,
+    path: &str,
+    options: &PatcherOptions,
+    report: &mut Report,
+) -> Result<bool, PatchError> {
+    if !Path::new(path).exists() {
+        return Err(PatchError::IOError(format!(
+            "File to delete not found: {path}"
+        )));
+    }
+    let removed_lines: Vec<String> = file_diff
+        .hunks
+        .iter()
+        .flat_map(|h| &h.lines)
+        .filter_map(|line| match line {
+            Line::Context(text) | Line::Removal(text) => Some(text.clone()),
+            Line::Addition(_) => None,
+        })
+        .collect();
+    let unchecked = removed_lines.is_empty() || is_binary(Path::new(path)).unwrap_or(false);
+    let reason = if unchecked {
+        // Formats like `*** Delete File:` carry no content to compare.
+        format!("The diff deletes {path} without giving its content, so it cannot be checked.")
+    } else {
+        let source_lines: Vec<String> = fs::read_to_string(path)?
+            .lines()
+            .map(String::from)
+            .collect();
+        let (common, total) = if options.fuzziness == 0 {
+            let total = removed_lines.len().max(source_lines.len());
+            let common = if removed_lines == source_lines {
+                total
+            } else {
+                0
+            };
+            (common, total)
+        } else {
+            patcher::deletion_match(&removed_lines, &source_lines)
+        };
+        if common == total {
+            return Ok(true);
+        }
+        let score = common as f32 / total as f32;
+        let required = if options.fuzziness == 0 {
+            1.0
+        } else {
+            options.match_threshold
+        };
+        if score >= required && !options.confirm {
+            report.warn(
+                WarningCode::PartialDeletion,
+                format!(
+                    "Deleted {path} although the diff only partly matches it ({common} of {total} lines)"
+                ),
+            );
+            return Ok(true);
+        }
+        format!(
+            "The diff deletes {path}, but its {} removed lines do not match the {} lines of the file ({common} of {total} lines in common).",
+            removed_lines.len(),
+            source_lines.len()
+        )
+    };
+
+    if options.ci || options.silent {
+        return Err(PatchError::HunkApplicationFailed {
+            file_path: path.to_string(),
+            hunk_index: 0,
+            reason,
+        });
+    }
+    eprintln!("[ERROR] {reason}");
+    loop {
+        eprintln!("Do you want to [d]elete it anyway, [s]kip this file, or [a]bort? (d/s/a)");
+        match read_user_input().to_lowercase().as_str() {
+            "d" => {
+                if unchecked {
+                    report.warn(
+                        WarningCode::UncheckedDeletion,
+                        format!("Deleted {path} without checking its content"),
+                    );
+                }
+                return Ok(true);
+            }
+            "s" => {
+                for i in 0..file_diff.hunks.len() {
+                    report.record_hunk(HunkRecord::unplaced(i, HunkStatus::Skipped));
+                }
+                return Ok(false);
+            }
+            "a" => {
+                return Err(PatchError::HunkApplicationFailed {
+                    file_path: path.to_string(),
+                    hunk_index: 0,
+                    reason: "User aborted the deletion.".to_string(),
+                });
+            }
+            _ => eprintln!("Invalid choice. Please enter 'd', 's', or 'a'."),
+        }
+    }
+}
+
+fn choose_existing_file_action(
+    path: &str,
+    options: &PatcherOptions,
+) -> Result<ExistingFileAction, PatchError> {
+    if let Some(action) = options.on_existing {
+        return Ok(action);
+    }
+    if options.ci || options.silent {
+        return Ok(ExistingFileAction::Fail);
+    }
+    eprintln!("[WARNING] The diff creates {path}, but the file already exists.");
+    loop {
+        eprintln!(
+            "Do you want to [o]verwrite it, [m]erge the new content into it, write to a [n]ew path, or [a]bort? (o/m/n/a)"
+        );
+        match read_user_input().to_lowercase().as_str() {
+            "o" => return Ok(ExistingFileAction::Overwrite),
+            "m" => return Ok(ExistingFileAction::Merge),
+            "n" => return Ok(ExistingFileAction::Alternate),
+            "a" => return Ok(ExistingFileAction::Fail),
+            _ => eprintln!("Invalid choice. Please enter 'o', 'm', 'n', or 'a'."),
+        }
+    }
+}
+
+/// Turns a diff creating `path` into a diff against its existing content: a
+/// lazy edit when the new content has elision markers, a whole-file
+/// replacement otherwise.
+fn merge_into_existing(
+    file_diff: &FileDiff,
+    path: &str,
+    options: &PatcherOptions,
+) -> Result<FileDiff, PatchError> {
+    let existing: Vec<String> = fs::read_to_string(path)?
+        .lines()
+        .map(String::from)
+        .collect();
+    let added: Vec<String> = file_diff
+        .hunks
+        .iter()
+        .flat_map(|h| &h.lines)
+        .filter_map(|line| match line {
+            Line::Addition(text) | Line::Context(text) => Some(text.clone()),
+            Line::Removal(_) => None,
+        })
+        .collect();
+
+    if added.iter().any(|line| options.elision.is_marker(line)) {
+        return lazy_edit::build_lazy_edit_diff(
+            &added.join("\n"),
+            &existing,
+            path,
+            &options.elision,
+            match_options(options),
+        )
+        .map_err(|e| PatchError::HunkApplicationFailed {
+            file_path: path.to_string(),
+            hunk_index: 0,
+            reason: format!("Could not merge the new content into the existing file: {e}"),
+        });
+    }
+    let block = whole_file::FileBlock {
+        path: path.to_string(),
+        lines: added,
+    };
+    Ok(whole_file::build_whole_file_diff(&block, Some(&existing)))
+}
+
+/// Remembers a choice made at a prompt for `--record`, under the hunk and
+/// file content hashes in `key`.
+fn record_decision(
+    report: &mut Report,
+    options: &PatcherOptions,
+    file: &str,
+    key: Option<&(String, String)>,
+    choice: Choice,
+) {
+    if let Some((hunk, source)) = key
+        && options.record
+    {
+        report.decisions.push(Decision {
+            file: file.to_string(),
+            hunk: hunk.clone(),
+            source: source.clone(),
+            choice,
+        });
+    }
+}
+
+/// Writes `hunk` into the file as a conflict block, at `best` or else at the
+/// best location found below the match threshold.
+fn mark_conflict(
+    source_lines: &mut Vec<String>,
+    hunk: &Hunk,
+    best: Option<&patcher::HunkMatch>,
+    cursor: &mut HunkCursor,
+    style: ConflictStyle,
+    hunk_index: usize,
+    options: &PatcherOptions,
+) {
+    let location = best.cloned().or_else(|| {
+        conflict::find_best_location(
+            source_lines,
+            hunk,
+            patcher::MatchOptions {
+                min_line: cursor.min_line,
+                ..match_options(options)
+            },
+        )
+    });
+    let (start, matched_length) = match location {
+        Some(m) => (m.start_index, m.matched_length),
+        None => (cursor.anchorless_position(hunk, source_lines.len()), 0),
+    };
+    let block_len = conflict::insert_conflict(
+        source_lines,
+        hunk,
+        start,
+        matched_length,
+        style,
+        &format!("diff (hunk {})", hunk_index + 1),
+    );
+    cursor.min_line = start + block_len;
+}
+
+/// Merges a git diff into `source_lines` using the base blob named by its
+/// `index` line, when the local repository has it. Returns the merged lines
+/// only when the merge is clean; otherwise the hunks are matched one by one.
+fn merge_with_base(
+    file_diff: &FileDiff,
+    source_lines: &[String],
+    options: &PatcherOptions,
+) -> Option<Vec<String>> {
+    let repo = Path::new(options.root.as_deref().unwrap_or("."));
+    let base = three_way::read_git_blob(repo, file_diff.old_blob.as_deref()?)?;
+    let Some(theirs) = three_way::apply_to_base(&base, file_diff) else {
+        if options.debug_mode {
+            eprintln!(
+                "[DEBUG] The diff does not apply to base blob {}, falling back to fuzzy matching.",
+                file_diff.old_blob.as_deref().unwrap_or_default()
+            );
+        }
+        return None;
+    };
+    // The file is still the diff's original: nothing to merge.
+    if source_lines == base {
+        return Some(theirs);
+    }
+    let merged = three_way::merge3(&base, source_lines, &theirs);
+    if merged.conflicts > 0 {
+        if options.debug_mode {
+            eprintln!(
+                "[DEBUG] Three-way merge left {} conflict(s), falling back to fuzzy matching.",
+                merged.conflicts
+            );
+        }
+        return None;
+    }
+    Some(merged.lines)
 }
 
 fn resolve_file_diff_interactively(
@@ -223,71 +944,291 @@ fn resolve_file_diff_interactively(
     options: &PatcherOptions,
     report: &mut Report,
 ) -> Result<Option<FilePatchResult>, PatchError> {
-    let old_path = cli_target_path
+    let mut old_path = cli_target_path
         .clone()
         .unwrap_or_else(|| file_diff.old_file.clone());
-    let new_path = cli_target_path
+    let mut new_path = cli_target_path
         .clone()
         .unwrap_or_else(|| file_diff.new_file.clone());
     if old_path.is_empty() && new_path != "/dev/null" {
-        return Err(PatchError::IOError("Could not determine target file. The diff has no file headers. Please specify the target file: `mend <TARGET_FILE> [DIFF_FILE]`".to_string()));
+        let found = locate_headerless_target(file_diff, options, report)?;
+        old_path = found.clone();
+        new_path = found;
+    }
+    let mut merged_diff = None;
+    if old_path == "/dev/null" && Path::new(&new_path).exists() {
+        match choose_existing_file_action(&new_path, options)? {
+            ExistingFileAction::Fail => {
+                return Err(PatchError::HunkApplicationFailed {
+                    file_path: new_path.clone(),
+                    hunk_index: 0,
+                    reason:
+                        "The diff creates this file, but it already exists (see --on-existing)."
+                            .to_string(),
+                });
+            }
+            ExistingFileAction::Overwrite => report.warn(
+                WarningCode::OverwroteExisting,
+                format!("Overwrote existing file {new_path}"),
+            ),
+            ExistingFileAction::Merge => {
+                merged_diff = Some(merge_into_existing(file_diff, &new_path, options)?);
+                old_path = new_path.clone();
+            }
+            ExistingFileAction::Alternate => {
+                let alternate = paths::alternate_path(&new_path);
+                report.warn(
+                    WarningCode::AlternatePath,
+                    format!("{new_path} already exists, wrote the new file to {alternate}"),
+                );
+                new_path = alternate;
+            }
+        }
     }
+    let file_diff = merged_diff.as_ref().unwrap_or(file_diff);
+
     if new_path == "/dev/null" {
-        return Ok(Some(FilePatchResult::Deleted { path: old_path }));
+        return Ok(verify_deletion(file_diff, &old_path, options, report)?
+            .then_some(FilePatchResult::Deleted { path: old_path }));
     }
+    let mut ends_with_newline = true;
     let mut source_lines: Vec<String> = if old_path == "/dev/null" {
         Vec::new()
     } else {
-        let path = Path::new(&old_path);
-        if !path.exists() {
-            return Err(PatchError::IOError(format!(
-                "Original file not found: {}",
-                path.display()
-            )));
+        if !Path::new(&old_path).exists() {
+            if cli_target_path.is_some() {
+                return Err(PatchError::IOError(format!(
+                    "Original file not found: {old_path}"
+                )));
+            }
+            let found = locate_missing_file(&old_path, file_diff, options, report)?;
+            if new_path == old_path {
+                new_path = found.clone();
+            }
+            old_path = found;
         }
+        let path = Path::new(&old_path);
         if is_binary(path).unwrap_or(false) {
-            report
-                .warnings
-                .push(format!("Skipped binary file: {old_path}"));
+            report.warn(
+                WarningCode::SkippedBinary,
+                format!("Skipped binary file: {old_path}"),
+            );
             return Ok(None);
         }
-        fs::read_to_string(path)?
-            .lines()
-            .map(String::from)
-            .collect()
+        let content = fs::read_to_string(path)?;
+        ends_with_newline = content.is_empty() || content.ends_with('\n');
+        content.lines().map(String::from).collect()
     };
 
-    let mut min_line = 0;
+    let mut cursor = HunkCursor::default();
+    let mut rejected: Vec<Hunk> = Vec::new();
+
+    let merged = if old_path == "/dev/null" || !options.three_way {
+        None
+    } else {
+        merge_with_base(file_diff, &source_lines, options)
+    };
+    let remaining_hunks = if let Some(merged) = merged {
+        source_lines = merged;
+        for i in 0..file_diff.hunks.len() {
+            report.record_hunk(HunkRecord {
+                strategy: Some(MatchStrategy::ThreeWay),
+                ..HunkRecord::unplaced(i, HunkStatus::Applied)
+            });
+        }
+        &[][..]
+    } else {
+        &file_diff.hunks[..]
+    };
 
-    for (i, hunk) in file_diff.hunks.iter().enumerate() {
+    for (i, original_hunk) in remaining_hunks.iter().enumerate() {
+        let expanded_hunk = expand_hunk_elisions(&source_lines, original_hunk, &cursor, options);
+        let hunk = expanded_hunk.as_ref().unwrap_or(original_hunk);
+        let decision_key = (options.record || options.replay.is_some()).then(|| {
+            (
+                decisions::hunk_hash(original_hunk),
+                decisions::content_hash(&source_lines),
+            )
+        });
         loop {
             let mut possible_matches =
-                patcher::find_strict_match(&source_lines, hunk, min_line, options.debug_mode);
+                find_candidate_matches(&source_lines, hunk, cursor.min_line, options);
+
+            if possible_matches.is_empty() && hunk.old_start == 0 && cursor.min_line > 0 {
+                // Hunks without line numbers (e.g. SEARCH/REPLACE blocks) carry
+                // no ordering, so they may also match above the previous hunk.
+                possible_matches = find_candidate_matches(&source_lines, hunk, 0, options);
+            }
 
-            if possible_matches.is_empty() && options.fuzziness > 0 {
-                let (clean_source_map, clean_index_map) =
-                    patcher::build_lookup_tables(&source_lines);
+            if hunk.anchor_len() == 0 {
+                // Pure insertions carry no content to search for, so they are
+                // placed by line number, shifted by the drift observed so far.
+                for m in &mut possible_matches {
+                    m.start_index = cursor.anchorless_position(hunk, source_lines.len());
+                }
+            }
+
+            let mut below_auto_accept = 0;
+            if let Some(min_score) = options.auto_accept_score
+                && (options.ci || options.silent)
+            {
+                let found = possible_matches.len();
+                possible_matches.retain(|m| m.score >= min_score);
+                below_auto_accept = found - possible_matches.len();
+            }
 
-                possible_matches = patcher::find_fuzzy_match(
-                    &source_lines,
-                    &clean_source_map,
-                    &clean_index_map,
+            // A hunk whose old side is nowhere but whose new side is in the
+            // file was most likely applied before.
+            if hunk.anchor_len() > 0
+                && possible_matches.is_empty()
+                && let Some(applied) = patcher::find_applied_match(&source_lines, hunk)
+            {
+                report.record_hunk(HunkRecord::placed(
+                    i,
+                    HunkStatus::AlreadyApplied,
                     hunk,
-                    patcher::MatchOptions {
-                        fuzziness: options.fuzziness,
-                        min_line,
-                        debug_mode: options.debug_mode,
-                        match_threshold: options.match_threshold,
-                    },
+                    &applied,
+                ));
+                report.warn(
+                    WarningCode::AlreadyApplied,
+                    format!(
+                        "Hunk {} in '{}' is already applied at line {}, skipped it.",
+                        i + 1,
+                        new_path,
+                        applied.start_index + 1
+                    ),
+                );
+                break;
+            }
+
+            if let Some(log) = &options.replay
+                && let Some((hunk_key, source_key)) = &decision_key
+                && let Some(decision) = log.find(&new_path, hunk_key, source_key)
+            {
+                match decisions::replay(decision, source_key, &possible_matches) {
+                    Replay::Apply(chosen_match) => apply_chosen_match(
+                        &mut source_lines,
+                        hunk,
+                        &chosen_match,
+                        &mut cursor,
+                        report,
+                        i,
+                        &new_path,
+                    ),
+                    Replay::Skip => {
+                        report.record_hunk(HunkRecord::unplaced(i, HunkStatus::Skipped));
+                        rejected.push(original_hunk.clone());
+                    }
+                    Replay::Stale => {
+                        report.record_hunk(HunkRecord::unplaced(i, HunkStatus::Failed));
+                        return Err(PatchError::HunkApplicationFailed {
+                            file_path: new_path.clone(),
+                            hunk_index: i,
+                            reason: "The file changed since the decision for this hunk was \
+                                     recorded."
+                                .to_string(),
+                        });
+                    }
+                }
+                break;
+            }
+
+            if possible_matches.len() > 1
+                && let Some(policy) = options.on_ambiguous
+            {
+                let Some(chosen) =
+                    patcher::pick_match(&possible_matches, policy, cursor.expected_position(hunk))
+                else {
+                    report.record_hunk(HunkRecord::unplaced(i, HunkStatus::Failed));
+                    return Err(PatchError::AmbiguousMatch {
+                        file_path: new_path.clone(),
+                        hunk_index: i,
+                    });
+                };
+                report.warn(
+                    WarningCode::AmbiguityResolved,
+                    format!(
+                        "Hunk {} in '{}' matched {} places, took line {} (--on-ambiguous {})",
+                        i + 1,
+                        new_path,
+                        possible_matches.len(),
+                        chosen.start_index + 1,
+                        policy
+                            .to_possible_value()
+                            .expect("every policy has a name")
+                            .get_name()
+                    ),
+                );
+                possible_matches = vec![chosen];
+            }
+
+            if let Some(style) = options.conflict_style
+                && (possible_matches.is_empty()
+                    || (possible_matches.len() > 1 && (options.ci || options.silent)))
+            {
+                mark_conflict(
+                    &mut source_lines,
+                    hunk,
+                    possible_matches.first(),
+                    &mut cursor,
+                    style,
+                    i,
+                    options,
                 );
+                report.record_hunk(HunkRecord::unplaced(i, HunkStatus::Conflict));
+                if !report.conflict_files.contains(&new_path) {
+                    report.conflict_files.push(new_path.clone());
+                }
+                break;
             }
 
             if possible_matches.is_empty() {
+                match options.on_failed {
+                    Some(FailedHunkAction::Fail) => {
+                        report.record_hunk(HunkRecord::unplaced(i, HunkStatus::Failed));
+                        return Err(PatchError::HunkApplicationFailed {
+                            file_path: new_path.clone(),
+                            hunk_index: i,
+                            reason: "No matching context found.".to_string(),
+                        });
+                    }
+                    Some(FailedHunkAction::Skip) => {
+                        report.record_hunk(HunkRecord::unplaced(i, HunkStatus::Skipped));
+                        report.warn(
+                            WarningCode::SkippedHunk,
+                            format!(
+                                "Hunk {} in '{}' matched nowhere, skipped it (--on-failed skip)",
+                                i + 1,
+                                new_path
+                            ),
+                        );
+                        break;
+                    }
+                    Some(FailedHunkAction::Reject) => {
+                        report.record_hunk(HunkRecord::unplaced(i, HunkStatus::Skipped));
+                        rejected.push(original_hunk.clone());
+                        break;
+                    }
+                    None => {}
+                }
+                if options.reject && (options.ci || options.silent) {
+                    report.record_hunk(HunkRecord::unplaced(i, HunkStatus::Skipped));
+                    rejected.push(original_hunk.clone());
+                    break;
+                }
                 if options.ci || options.silent {
+                    report.record_hunk(HunkRecord::unplaced(i, HunkStatus::Failed));
+                    let reason = if below_auto_accept > 0 {
+                        format!(
+                            "{below_auto_accept} match(es) found, all below --auto-accept-score."
+                        )
+                    } else {
+                        "No matching context found in CI mode.".to_string()
+                    };
                     return Err(PatchError::HunkApplicationFailed {
                         file_path: new_path.clone(),
                         hunk_index: i,
-                        reason: "No matching context found in CI mode.".to_string(),
+                        reason,
                     });
                 }
                 eprintln!(
@@ -298,9 +1239,18 @@ fn resolve_file_diff_interactively(
                 eprintln!("Do you want to [s]kip this hunk or [a]bort the process? (s/a)");
                 let choice = read_user_input();
                 if choice.to_lowercase() == "s" {
-                    report.hunks_skipped += 1;
+                    record_decision(
+                        report,
+                        options,
+                        &new_path,
+                        decision_key.as_ref(),
+                        Choice::Skip,
+                    );
+                    report.record_hunk(HunkRecord::unplaced(i, HunkStatus::Skipped));
+                    rejected.push(original_hunk.clone());
                     break;
                 } else if choice.to_lowercase() == "a" {
+                    report.record_hunk(HunkRecord::unplaced(i, HunkStatus::Failed));
                     return Err(PatchError::HunkApplicationFailed {
                         file_path: new_path.clone(),
                         hunk_index: i,
@@ -311,7 +1261,13 @@ fn resolve_file_diff_interactively(
                     continue;
                 }
             } else if possible_matches.len() > 1 {
+                if options.reject && (options.ci || options.silent) {
+                    report.record_hunk(HunkRecord::unplaced(i, HunkStatus::Skipped));
+                    rejected.push(original_hunk.clone());
+                    break;
+                }
                 if options.ci || options.silent {
+                    report.record_hunk(HunkRecord::unplaced(i, HunkStatus::Failed));
                     return Err(PatchError::AmbiguousMatch {
                         file_path: new_path.clone(),
                         hunk_index: i,
@@ -330,9 +1286,18 @@ fn resolve_file_diff_interactively(
                 );
                 let choice = read_user_input();
                 if choice.to_lowercase() == "s" {
-                    report.hunks_skipped += 1;
+                    record_decision(
+                        report,
+                        options,
+                        &new_path,
+                        decision_key.as_ref(),
+                        Choice::Skip,
+                    );
+                    report.record_hunk(HunkRecord::unplaced(i, HunkStatus::Skipped));
+                    rejected.push(original_hunk.clone());
                     break;
                 } else if choice.to_lowercase() == "a" {
+                    report.record_hunk(HunkRecord::unplaced(i, HunkStatus::Failed));
                     return Err(PatchError::AmbiguousMatch {
                         file_path: new_path.clone(),
                         hunk_index: i,
@@ -340,34 +1305,24 @@ fn resolve_file_diff_interactively(
                 } else if let Ok(index) = choice.parse::<usize>() {
                     if index > 0 && index <= possible_matches.len() {
                         let chosen_match = &possible_matches[index - 1];
-                        if chosen_match.score < 0.9 {
-                            report.warnings.push(format!(
-                                "Hunk {} in '{}' was applied with a fuzzy match score ({:.2}). Please review.",
-                                i + 1,
-                                new_path,
-                                chosen_match.score
-                            ));
-                        }
-
-                        report.hunks_applied += 1;
-                        source_lines = patcher::apply_hunk(
-                            &source_lines,
+                        record_decision(
+                            report,
+                            options,
+                            &new_path,
+                            decision_key.as_ref(),
+                            Choice::Apply {
+                                line: chosen_match.start_index + 1,
+                            },
+                        );
+                        apply_chosen_match(
+                            &mut source_lines,
                             hunk,
-                            chosen_match.start_index,
-                            chosen_match.matched_length,
+                            chosen_match,
+                            &mut cursor,
+                            report,
+                            i,
+                            &new_path,
                         );
-                        let hunk_new_lines_count = hunk
-                            .lines
-                            .iter()
-                            .filter(|l| {
-                                matches!(
-                                    l,
-                                    mend::diff::Line::Context(_) | mend::diff::Line::Addition(_)
-                                )
-                            })
-                            .count();
-                        min_line = chosen_match.start_index + hunk_new_lines_count;
-
                         break;
                     } else {
                         eprintln!("Invalid index. Please enter a valid number, 's', or 'a'.");
@@ -379,7 +1334,11 @@ fn resolve_file_diff_interactively(
                 }
             } else {
                 let chosen_match = &possible_matches[0];
-                if !options.ci && !options.silent && (options.confirm || chosen_match.score < 1.0) {
+                let auto_accept_score = options.auto_accept_score.unwrap_or(1.0);
+                if !options.ci
+                    && !options.silent
+                    && (options.confirm || chosen_match.score < auto_accept_score)
+                {
                     eprintln!(
                         "[INFO] Found a single match for hunk {} in file {}.",
                         i + 1,
@@ -389,38 +1348,38 @@ fn resolve_file_diff_interactively(
                     eprintln!("\nApply this hunk? [y]es, [s]kip, [a]bort (y/s/a)");
                     let choice = read_user_input();
                     if choice.to_lowercase() == "y" {
-                        if chosen_match.score < 0.9 {
-                            report.warnings.push(format!(
-                                "Hunk {} in '{}' was applied with a fuzzy match score ({:.2}). Please review.",
-                                i + 1,
-                                new_path,
-                                chosen_match.score
-                            ));
-                        }
-
-                        report.hunks_applied += 1;
-                        source_lines = patcher::apply_hunk(
-                            &source_lines,
+                        record_decision(
+                            report,
+                            options,
+                            &new_path,
+                            decision_key.as_ref(),
+                            Choice::Apply {
+                                line: chosen_match.start_index + 1,
+                            },
+                        );
+                        apply_chosen_match(
+                            &mut source_lines,
                             hunk,
-                            chosen_match.start_index,
-                            chosen_match.matched_length,
+                            chosen_match,
+                            &mut cursor,
+                            report,
+                            i,
+                            &new_path,
                         );
-                        let hunk_new_lines_count = hunk
-                            .lines
-                            .iter()
-                            .filter(|l| {
-                                matches!(
-                                    l,
-                                    mend::diff::Line::Context(_) | mend::diff::Line::Addition(_)
-                                )
-                            })
-                            .count();
-                        min_line = chosen_match.start_index + hunk_new_lines_count;
                         break;
                     } else if choice.to_lowercase() == "s" {
-                        report.hunks_skipped += 1;
+                        record_decision(
+                            report,
+                            options,
+                            &new_path,
+                            decision_key.as_ref(),
+                            Choice::Skip,
+                        );
+                        report.record_hunk(HunkRecord::unplaced(i, HunkStatus::Skipped));
+                        rejected.push(original_hunk.clone());
                         break;
                     } else if choice.to_lowercase() == "a" {
+                        report.record_hunk(HunkRecord::unplaced(i, HunkStatus::Failed));
                         return Err(PatchError::HunkApplicationFailed {
                             file_path: new_path.clone(),
                             hunk_index: i,
@@ -431,44 +1390,53 @@ fn resolve_file_diff_interactively(
                         continue;
                     }
                 } else {
-                    if chosen_match.score < 0.9 {
-                        report.warnings.push(format!(
-                            "Hunk {} in '{}' was applied with a fuzzy match score ({:.2}). Please review.",
-                            i + 1,
-                            new_path,
-                            chosen_match.score
-                        ));
-                    }
-
-                    report.hunks_applied += 1;
-                    source_lines = patcher::apply_hunk(
-                        &source_lines,
+                    apply_chosen_match(
+                        &mut source_lines,
                         hunk,
-                        chosen_match.start_index,
-                        chosen_match.matched_length,
+                        chosen_match,
+                        &mut cursor,
+                        report,
+                        i,
+                        &new_path,
                     );
-                    let hunk_new_lines_count = hunk
-                        .lines
-                        .iter()
-                        .filter(|l| {
-                            matches!(
-                                l,
-                                mend::diff::Line::Context(_) | mend::diff::Line::Addition(_)
-                            )
-                        })
-                        .count();
-                    min_line = chosen_match.start_index + hunk_new_lines_count;
                     break;
                 }
             }
         }
     }
-    let new_content = source_lines.join("\n");
+    let write_rejects = options.reject || options.on_failed == Some(FailedHunkAction::Reject);
+    if write_rejects && !rejected.is_empty() {
+        let reject_path = format!("{new_path}.rej");
+        if let Some(record) = report.files.last_mut() {
+            record.reject_file = Some(reject_path.clone());
+        }
+        report.reject_files.push((
+            reject_path,
+            reject::format_reject_file(&old_path, &new_path, &rejected),
+        ));
+    }
+    // The file keeps its final newline, or lack of one, unless the diff
+    // changes it.
+    match file_diff.hunks.last() {
+        Some(hunk) if hunk.new_missing_newline => ends_with_newline = false,
+        Some(hunk) if hunk.old_missing_newline => ends_with_newline = true,
+        _ => {}
+    }
+    let mut new_content = source_lines.join("\n");
+    if ends_with_newline && !source_lines.is_empty() {
+        new_content.push('\n');
+    }
     if old_path == "/dev/null" {
         Ok(Some(FilePatchResult::Created {
             path: new_path,
             new_content,
         }))
+    } else if file_diff.is_rename && cli_target_path.is_none() {
+        Ok(Some(FilePatchResult::Renamed {
+            old_path,
+            path: new_path,
+            new_content,
+        }))
     } else {
         Ok(Some(FilePatchResult::Modified {
             path: new_path,
@@ -493,6 +1461,19 @@ fn apply_changes(results: &[FilePatchResult]) -> io::Result<()> {
             FilePatchResult::Deleted { path } => {
                 fs::remove_file(path)?;
             }
+            FilePatchResult::Renamed {
+                old_path,
+                path,
+                new_content,
+            } => {
+                if let Some(parent) = Path::new(path).parent()
+                    && !parent.exists()
+                {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::write(path, new_content)?;
+                fs::remove_file(old_path)?;
+            }
         }
     }
     Ok(())
@@ -532,6 +1513,143 @@ fn get_diff_content(args: &Args) -> Result<String, AppError> {
     Ok(diff_content)
 }
 
+fn build_elision_matcher(args: &Args) -> Result<ElisionMatcher, AppError> {
+    if args.no_elision {
+        return Ok(ElisionMatcher::disabled());
+    }
+    ElisionMatcher::new(&args.elision_pattern).map_err(|e| AppError::InvalidPattern(e.to_string()))
+}
+
+fn print_whole_file_preview(file_diff: &FileDiff) {
+    const CONTEXT_LINES: usize = 2;
+    let Some(hunk) = file_diff.hunks.first() else {
+        return;
+    };
+    let is_change = |line: &Line| !matches!(line, Line::Context(_));
+    let mut last_printed: Option<usize> = None;
+    for (idx, line) in hunk.lines.iter().enumerate() {
+        let window_end = (idx + CONTEXT_LINES + 1).min(hunk.lines.len());
+        let near_change = hunk.lines[idx.saturating_sub(CONTEXT_LINES)..window_end]
+            .iter()
+            .any(is_change);
+        if !near_change {
+            continue;
+        }
+        if last_printed.is_some_and(|last| last + 1 < idx) {
+            eprintln!("  ...");
+        }
+        match line {
+            Line::Context(text) => eprintln!("   {text}"),
+            Line::Removal(text) => eprintln!("  -{text}"),
+            Line::Addition(text) => eprintln!("  +{text}"),
+        }
+        last_printed = Some(idx);
+    }
+}
+
+/// Rejects a path named by the diff that leaves `--root`, unless
+/// `--unsafe-paths` is given or the user named the target file.
+fn validate_path(args: &Args, path: &str) -> Result<(), AppError> {
+    if args.target_file.is_some() || args.unsafe_paths || path.is_empty() || path == "/dev/null" {
+        return Ok(());
+    }
+    let root = Path::new(args.root.as_deref().unwrap_or("."));
+    paths::validate_diff_path(root, args.directory.as_deref(), path).map_err(|reason| {
+        AppError::UnsafePath {
+            path: path.to_string(),
+            reason,
+        }
+    })
+}
+
+/// Turns fenced code blocks holding whole files into a patch, after showing
+/// what would change and checking that no block looks truncated.
+fn prepare_whole_file_patch(diff_content: &str, args: &Args) -> Result<Patch, AppError> {
+    let mut blocks = whole_file::extract_file_blocks(diff_content);
+    if let Some(target) = &args.target_file
+        && blocks.len() == 1
+    {
+        blocks[0].path = target.clone();
+    } else if let Some(directory) = &args.directory {
+        for block in &mut blocks {
+            block.path = paths::prepend_directory(directory, &block.path);
+        }
+    }
+    let elision = build_elision_matcher(args)?;
+    let interactive = !args.ci && !args.silent;
+
+    let mut patch = Patch::default();
+    for block in &blocks {
+        // Checked before the file is read or shown.
+        validate_path(args, &block.path)?;
+        let on_disk = match (&args.root, &args.target_file) {
+            (Some(root), None) => Path::new(root).join(&block.path),
+            _ => PathBuf::from(&block.path),
+        };
+        let old_lines: Option<Vec<String>> = if on_disk.exists() {
+            Some(
+                fs::read_to_string(&on_disk)?
+                    .lines()
+                    .map(String::from)
+                    .collect(),
+            )
+        } else {
+            None
+        };
+        let file_diff = whole_file::build_whole_file_diff(block, old_lines.as_deref());
+        if file_diff.hunks.is_empty() {
+            continue;
+        }
+        let warnings = whole_file::truncation_warnings(
+            &block.path,
+            old_lines.as_deref(),
+            &block.lines,
+            &elision,
+        );
+
+        if !interactive {
+            if !warnings.is_empty() {
+                return Err(AppError::SuspiciousWholeFile {
+                    path: block.path.clone(),
+                    warnings,
+                });
+            }
+            patch.diffs.push(file_diff);
+            continue;
+        }
+
+        eprintln!(
+            "[INFO] The input holds the whole content of '{}' ({} -> {} lines):",
+            block.path,
+            old_lines.as_ref().map_or(0, |l| l.len()),
+            block.lines.len()
+        );
+        print_whole_file_preview(&file_diff);
+        for warning in &warnings {
+            eprintln!("[WARNING] {warning}");
+        }
+        loop {
+            eprintln!("\nReplace this file? [y]es, [s]kip, [a]bort (y/s/a)");
+            match read_user_input().to_lowercase().as_str() {
+                "y" => {
+                    patch.diffs.push(file_diff);
+                    break;
+                }
+                "s" => break,
+                "a" => {
+                    return Err(AppError::Patch(PatchError::HunkApplicationFailed {
+                        file_path: block.path.clone(),
+                        hunk_index: 0,
+                        reason: "User aborted the whole-file replacement.".to_string(),
+                    }));
+                }
+                _ => eprintln!("Invalid choice. Please enter 'y', 's', or 'a'."),
+            }
+        }
+    }
+    Ok(patch)
+}
+
 fn process_patch(
     patch: &Patch,
     args: &Args,
@@ -544,6 +1662,34 @@ fn process_patch(
         ci: args.ci,
         silent: args.silent,
         match_threshold: args.match_threshold,
+        elision: build_elision_matcher(args)?,
+        on_existing: args.on_existing,
+        reject: args.reject,
+        conflict_style: args.conflict_style,
+        on_ambiguous: args.on_ambiguous,
+        on_failed: args.on_failed,
+        auto_accept_score: args.auto_accept_score,
+        record: args.record.is_some(),
+        replay: args
+            .replay
+            .as_deref()
+            .map(|path| {
+                DecisionLog::load(Path::new(path)).map_err(|e| match e.kind() {
+                    io::ErrorKind::InvalidData => AppError::InvalidDecisionFile {
+                        path: path.to_string(),
+                        reason: e.to_string(),
+                    },
+                    _ => AppError::Io(e),
+                })
+            })
+            .transpose()?,
+        root: args.root.clone(),
+        // Prompts are answered, recorded and replayed hunk by hunk, which a
+        // merge of the whole file bypasses.
+        three_way: !args.no_three_way
+            && !args.confirm
+            && args.record.is_none()
+            && args.replay.is_none(),
     };
 
     let mut all_patch_results: Vec<FilePatchResult> = Vec::new();
@@ -558,18 +1704,113 @@ fn process_patch(
                 args.target_file.as_deref().unwrap_or(&file_diff.new_file)
             );
         }
-        if let Some(result) =
-            resolve_file_diff_interactively(file_diff, &args.target_file, &options, report)?
-        {
-            all_patch_results.push(result);
+        let path = match &args.target_file {
+            Some(target) => target.clone(),
+            None if file_diff.new_file == "/dev/null" => file_diff.old_file.clone(),
+            None => file_diff.new_file.clone(),
+        };
+        // Stays `Failed` if the section aborts the run.
+        report.files.push(FileRecord {
+            path,
+            old_path: None,
+            status: FileStatus::Failed,
+            reject_file: None,
+            hunks: Vec::new(),
+        });
+        let result =
+            resolve_file_diff_interactively(file_diff, &args.target_file, &options, report)?;
+        let record = report.files.last_mut().expect("pushed above");
+        match &result {
+            Some(FilePatchResult::Modified { path, .. }) => {
+                record.status = FileStatus::Modified;
+                record.path = path.clone();
+            }
+            Some(FilePatchResult::Created { path, .. }) => {
+                record.status = FileStatus::Created;
+                record.path = path.clone();
+            }
+            Some(FilePatchResult::Deleted { path }) => {
+                record.status = FileStatus::Deleted;
+                record.path = path.clone();
+            }
+            Some(FilePatchResult::Renamed { old_path, path, .. }) => {
+                record.status = FileStatus::Renamed;
+                record.path = path.clone();
+                record.old_path = Some(old_path.clone());
+            }
+            None => record.status = FileStatus::Skipped,
         }
+        all_patch_results.extend(result);
     }
     Ok(all_patch_results)
 }
 
+/// The unified diff between the files on disk and the resolved results,
+/// showing where each hunk really landed. With `root`, the headers name the
+/// files relative to it, as the input diff did.
+fn render_changes(results: &[FilePatchResult], root: Option<&str>) -> io::Result<String> {
+    let mut out = String::new();
+    for result in results {
+        let (old_path, new_path, old, new) = match result {
+            FilePatchResult::Modified { path, new_content } => (
+                path.as_str(),
+                path.as_str(),
+                fs::read_to_string(path)?,
+                new_content.as_str(),
+            ),
+            FilePatchResult::Created { path, new_content } => (
+                "/dev/null",
+                path.as_str(),
+                String::new(),
+                new_content.as_str(),
+            ),
+            FilePatchResult::Deleted { path } => {
+                (path.as_str(), "/dev/null", fs::read_to_string(path)?, "")
+            }
+            FilePatchResult::Renamed {
+                old_path,
+                path,
+                new_content,
+            } => (
+                old_path.as_str(),
+                path.as_str(),
+                fs::read_to_string(old_path)?,
+                new_content.as_str(),
+            ),
+        };
+        let label = |path: &str| match root {
+            Some(root) => paths::strip_directory(root, path),
+            None => path.to_string(),
+        };
+        out.push_str(&unified::git_diff(
+            &label(old_path),
+            &label(new_path),
+            &old,
+            new,
+            unified::DEFAULT_CONTEXT,
+        ));
+    }
+    Ok(out)
+}
+
+/// Prints a unified diff, colored when standard output is a terminal.
+fn print_diff(diff: &str) {
+    if io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none() {
+        print!("{}", unified::colorize(diff));
+    } else {
+        print!("{diff}");
+    }
+}
+
+fn print_changes(results: &[FilePatchResult]) -> io::Result<()> {
+    print_diff(&render_changes(results, None)?);
+    Ok(())
+}
+
 fn handle_results(
     results: &[FilePatchResult],
     dry_run: bool,
+    emit_diff: bool,
     silent: bool,
     revert: bool,
     report: &mut Report,
@@ -579,6 +1820,7 @@ fn handle_results(
             FilePatchResult::Modified { .. } => report.files_modified += 1,
             FilePatchResult::Created { .. } => report.files_created += 1,
             FilePatchResult::Deleted { .. } => report.files_deleted += 1,
+            FilePatchResult::Renamed { .. } => report.files_renamed += 1,
         }
     }
 
@@ -589,14 +1831,37 @@ fn handle_results(
                 FilePatchResult::Modified { path, .. } => println!("  - [MODIFIED] {path}"),
                 FilePatchResult::Created { path, .. } => println!("  - [CREATED]  {path}"),
                 FilePatchResult::Deleted { path } => println!("  - [DELETED]  {path}"),
+                FilePatchResult::Renamed { old_path, path, .. } => {
+                    println!("  - [RENAMED]  {old_path} -> {path}")
+                }
             }
         }
+        for (path, _) in &report.reject_files {
+            println!("  - [REJECTS]  {path}");
+        }
+        println!();
+        print_changes(results)?;
+    } else if emit_diff {
+        print_changes(results)?;
     }
 
-    if !results.is_empty() {
+    // A run where every hunk was rejected has no result but still writes
+    // its `.rej` files.
+    if !results.is_empty() || !report.reject_files.is_empty() {
         let apply_start = Instant::now();
         if !dry_run {
             apply_changes(results)?;
+            for (path, content) in &report.reject_files {
+                fs::write(path, content)?;
+            }
+            let rejected: Vec<String> =
+                report.reject_files.iter().map(|(p, _)| p.clone()).collect();
+            for path in rejected {
+                report.warn(
+                    WarningCode::RejectsWritten,
+                    format!("Unresolved hunks were written to {path}"),
+                );
+            }
         }
         report.elapsed_ms = Some(apply_start.elapsed().as_millis());
         if !silent {
@@ -608,7 +1873,7 @@ fn handle_results(
     Ok(())
 }
 
-fn main_logic(mut args: Args) -> Result<(), AppError> {
+fn main_logic(mut args: Args, report: &mut Report) -> Result<(), AppError> {
     let is_verbose = (args.verbose || args.debug) && !args.silent;
 
     if !args.clipboard && args.target_file.is_some() && args.diff_file.is_none() {
@@ -626,7 +1891,105 @@ fn main_logic(mut args: Args) -> Result<(), AppError> {
         return Err(AppError::EmptyDiff);
     }
 
-    let mut patch = parser::parse_patch(&diff_content)?;
+    let format = parser::detect_format(&diff_content);
+    if is_verbose && !args.lazy {
+        println!("[INFO] Detected diff format: {format:?}");
+    }
+    let mut patch = match format {
+        _ if args.lazy => {
+            let target = args
+                .target_file
+                .as_ref()
+                .ok_or_else(|| AppError::TargetRequired {
+                    input_kind: "a lazy edit snippet".to_string(),
+                })?;
+            let source_lines: Vec<String> = fs::read_to_string(target)?
+                .lines()
+                .map(String::from)
+                .collect();
+            let file_diff = lazy_edit::build_lazy_edit_diff(
+                &diff_content,
+                &source_lines,
+                target,
+                &build_elision_matcher(&args)?,
+                patcher::MatchOptions {
+                    fuzziness: args.fuzziness,
+                    min_line: 0,
+                    debug_mode: args.debug,
+                    match_threshold: args.match_threshold,
+                },
+            )?;
+            Patch {
+                diffs: vec![file_diff],
+            }
+        }
+        DiffFormat::Unified => {
+            let strip_level = match args.strip {
+                Some(count) => Some(count),
+                None => {
+                    let base = Path::new(args.root.as_deref().unwrap_or("."))
+                        .join(args.directory.as_deref().unwrap_or(""));
+                    let base = base.as_path();
+                    let guessed = paths::guess_strip_level(&diff_content, base);
+                    if let Some(count) = guessed
+                        && is_verbose
+                    {
+                        println!("[INFO] Guessed strip level -p{count} from the files on disk.");
+                    }
+                    guessed
+                }
+            };
+            match strip_level {
+                Some(count) => {
+                    parser::parse_patch_with_strip(&diff_content, StripLevel::Components(count))?
+                }
+                None => parser::parse_patch(&diff_content)?,
+            }
+        }
+        DiffFormat::Normal => normal::parse_normal_diff(&diff_content)?,
+        DiffFormat::BeginPatch => begin_patch::parse_begin_patch(&diff_content)?,
+        DiffFormat::SearchReplace => search_replace::parse_search_replace(&diff_content)?,
+        DiffFormat::WholeFile => prepare_whole_file_patch(&diff_content, &args)?,
+        DiffFormat::Ed => {
+            let target = args
+                .target_file
+                .as_ref()
+                .ok_or_else(|| AppError::TargetRequired {
+                    input_kind: "an ed script".to_string(),
+                })?;
+            let original_lines: Vec<String> = fs::read_to_string(target)?
+                .lines()
+                .map(String::from)
+                .collect();
+            normal::parse_ed_script(&diff_content, &original_lines)?
+        }
+    };
+
+    if let Some(directory) = &args.directory
+        && args.target_file.is_none()
+        && format != DiffFormat::WholeFile
+    {
+        for diff in &mut patch.diffs {
+            diff.old_file = paths::prepend_directory(directory, &diff.old_file);
+            diff.new_file = paths::prepend_directory(directory, &diff.new_file);
+        }
+    }
+
+    for diff in &patch.diffs {
+        validate_path(&args, &diff.old_file)?;
+        validate_path(&args, &diff.new_file)?;
+    }
+
+    // Paths in the diff are relative to the root; from here on they are
+    // used as they are to read and write files.
+    if args.target_file.is_none()
+        && let Some(root) = &args.root
+    {
+        for diff in &mut patch.diffs {
+            diff.old_file = paths::prepend_directory(root, &diff.old_file);
+            diff.new_file = paths::prepend_directory(root, &diff.new_file);
+        }
+    }
 
     if args.revert {
         if is_verbose {
@@ -636,20 +1999,7 @@ fn main_logic(mut args: Args) -> Result<(), AppError> {
     }
 
     if let Some(target_path_str) = &args.target_file {
-        let target_path = PathBuf::from(target_path_str);
-        let target_filename = target_path.file_name().unwrap_or_default();
-
-        patch.diffs.retain(|diff| {
-            let old_filename = Path::new(&diff.old_file).file_name().unwrap_or_default();
-            let new_filename = Path::new(&diff.new_file).file_name().unwrap_or_default();
-            old_filename == target_filename || new_filename == target_filename
-        });
-
-        if patch.diffs.is_empty() {
-            return Err(AppError::NoMatchingChanges {
-                target_file: target_path_str.clone(),
-            });
-        }
+        paths::retain_target(&mut patch, target_path_str)?;
     }
 
     if is_verbose {
@@ -663,17 +2013,32 @@ fn main_logic(mut args: Args) -> Result<(), AppError> {
         );
     }
 
-    let mut report = Report::default();
-    let all_patch_results = process_patch(&patch, &args, &mut report)?;
+    let all_patch_results = process_patch(&patch, &args, report)?;
+
+    if let Some(Command::Normalize { output, .. }) = &args.command {
+        return write_normalized_patch(
+            &all_patch_results,
+            output.as_deref(),
+            args.root.as_deref(),
+            report,
+        );
+    }
 
     handle_results(
         &all_patch_results,
         args.dry_run || args.debug,
-        args.silent,
+        args.emit_diff,
+        args.silent || args.format == ReportFormat::Json,
         args.revert,
-        &mut report,
+        report,
     )?;
 
+    if !report.conflict_files.is_empty() {
+        return Err(AppError::ConflictsLeft {
+            files: report.conflict_files.clone(),
+        });
+    }
+
     if is_verbose {
         println!("----------------------------");
         println!("Execution finished.");
@@ -681,19 +2046,165 @@ fn main_logic(mut args: Args) -> Result<(), AppError> {
     Ok(())
 }
 
-fn run() -> Result<(), AppError> {
-    let args = Args::parse();
+/// Writes the resolved changes as a unified diff for `mend normalize`. The
+/// files themselves are left untouched.
+fn write_normalized_patch(
+    results: &[FilePatchResult],
+    output: Option<&str>,
+    root: Option<&str>,
+    report: &Report,
+) -> Result<(), AppError> {
+    let diff = render_changes(results, root)?;
+    match output {
+        Some(path) => fs::write(path, &diff)?,
+        None => print!("{diff}"),
+    }
+    if report.hunks_skipped > 0 {
+        eprintln!(
+            "[WARNING] {} hunk(s) could not be resolved and were left out.",
+            report.hunks_skipped
+        );
+    }
+    for warning in &report.warnings {
+        eprintln!("[WARNING] {}", warning.message);
+    }
+    if !report.conflict_files.is_empty() {
+        return Err(AppError::ConflictsLeft {
+            files: report.conflict_files.clone(),
+        });
+    }
+    Ok(())
+}
+
+/// Writes the diff from `old` to `new` for `mend diff`.
+fn generate_diff(
+    old: &str,
+    new: &str,
+    context: usize,
+    output: Option<&str>,
+) -> Result<(), AppError> {
+    let (patch, binary_files) = if old == "-" || new == "-" {
+        let mut stdin_content = String::new();
+        io::stdin().read_to_string(&mut stdin_content)?;
+        let (path, old_content, new_content) = if old == "-" {
+            (new, stdin_content, fs::read_to_string(new)?)
+        } else {
+            (old, fs::read_to_string(old)?, stdin_content)
+        };
+        let file_diff = unified::diff_contents(path, path, &old_content, &new_content, context);
+        let mut patch = Patch::default();
+        if !file_diff.hunks.is_empty() {
+            patch.diffs.push(file_diff);
+        }
+        (patch, Vec::new())
+    } else {
+        unified::diff_paths(Path::new(old), Path::new(new), context)?
+    };
+
+    for path in &binary_files {
+        eprintln!("[WARNING] Binary files differ: {path}");
+    }
+    let diff = patch.to_string();
+    match output {
+        Some(path) => fs::write(path, diff)?,
+        None => print_diff(&diff),
+    }
+    Ok(())
+}
+
+/// The `status` of the JSON report for an exit code.
+fn exit_status_name(code: i32) -> &'static str {
+    match code {
+        error::EXIT_SUCCESS => "success",
+        error::EXIT_INVALID_INPUT => "invalid-input",
+        error::EXIT_AMBIGUOUS => "ambiguous",
+        error::EXIT_IO => "io-error",
+        error::EXIT_PARTIAL => "partial",
+        error::EXIT_CONFLICTS => "conflicts",
+        error::EXIT_WARNINGS => "warnings",
+        _ => "failed",
+    }
+}
+
+/// Runs `mend` and returns the exit code of a run that did not fail.
+fn run() -> Result<i32, AppError> {
+    let mut args = Args::parse();
+    match &args.command {
+        Some(Command::Normalize { diff_file, output }) => {
+            if args.format == ReportFormat::Json && output.is_none() {
+                Args::command()
+                    .error(
+                        clap::error::ErrorKind::ArgumentConflict,
+                        "`normalize --format json` needs --output: the diff and the report \
+                         would both be printed to standard output",
+                    )
+                    .exit();
+            }
+            args.diff_file = diff_file.clone();
+            // Its output is meant for other tools, so it never prompts.
+            args.ci = true;
+        }
+        Some(Command::Diff {
+            old,
+            new,
+            context,
+            output,
+        }) => {
+            generate_diff(old, new, *context, output.as_deref())?;
+            return Ok(error::EXIT_SUCCESS);
+        }
+        None => {}
+    }
     if args.example {
         println!("This is an example diff, please follow the same format.\n");
         println!("{EXAMPLE_DIFF}");
-        return Ok(());
+        return Ok(error::EXIT_SUCCESS);
+    }
+    let format = args.format;
+    let report_file = args.report_file.clone();
+    let dry_run = args.dry_run || args.debug;
+    let fail_on_warning = args.fail_on_warning;
+    let record = args.record.clone();
+    let mut report = Report::default();
+    let result = main_logic(args, &mut report).map(|()| report.exit_code(fail_on_warning));
+
+    // Choices made before a failure are kept too, so the run can resume.
+    if let Some(path) = &record {
+        DecisionLog {
+            decisions: std::mem::take(&mut report.decisions),
+        }
+        .save(Path::new(path))?;
     }
-    main_logic(args)
+
+    if format == ReportFormat::Json || report_file.is_some() {
+        let exit_code = match &result {
+            Ok(code) => *code,
+            Err(e) => e.exit_code(),
+        };
+        let json = serde_json::to_string_pretty(&JsonReport {
+            status: exit_status_name(exit_code),
+            exit_code,
+            dry_run,
+            error: result.as_ref().err().map(ToString::to_string),
+            report: &report,
+        })
+        .expect("the report serializes to JSON");
+        if let Some(path) = &report_file {
+            fs::write(path, format!("{json}\n"))?;
+        }
+        if format == ReportFormat::Json {
+            println!("{json}");
+        }
+    }
+    result
 }
 
 fn main() {
-    if let Err(e) = run() {
-        eprintln!("[ERROR] {e}");
-        process::exit(1);
+    match run() {
+        Ok(code) => process::exit(code),
+        Err(e) => {
+            eprintln!("[ERROR] {e}");
+            process::exit(e.exit_code());
+        }
     }
 }
@@ -0,0 +1,169 @@
+use crate::diff::Line;
+
+/// One step of an edit script, by index into the old and new sequences.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Edit {
+    Unchanged(usize, usize),
+    Deletion(usize),
+    Insertion(usize),
+}
+
+/// Computes a shortest edit script turning `old` into `new` with Myers'
+/// O((N+M)D) algorithm, as a sequence of context, removal and addition
+/// lines. Within a change, removals come before additions.
+pub fn diff_lines(old: &[String], new: &[String]) -> Vec<Line> {
+    let mut lines = Vec::new();
+    let mut pending_additions = Vec::new();
+    for edit in diff(old, new) {
+        match edit {
+            Edit::Unchanged(a, _) => {
+                lines.append(&mut pending_additions);
+                lines.push(Line::Context(old[a].clone()));
+            }
+            Edit::Deletion(a) => lines.push(Line::Removal(old[a].clone())),
+            Edit::Insertion(b) => pending_additions.push(Line::Addition(new[b].clone())),
+        }
+    }
+    lines.append(&mut pending_additions);
+    lines
+}
+
+/// Computes a shortest edit script turning `old` into `new`, in order.
+///
+/// Uses the linear-space variant of Myers' algorithm: the middle of the
+/// edit path is found by searching from both ends at once, and the two
+/// halves are solved recursively. Memory stays O(N+M) however different the
+/// inputs are. The common prefix and suffix of every part are set aside
+/// first, so the work grows with the size of the changed regions rather than
+/// with the size of the inputs.
+pub fn diff<T: PartialEq>(old: &[T], new: &[T]) -> Vec<Edit> {
+    let mut edits = Vec::with_capacity(old.len().max(new.len()));
+    diff_into(old, new, 0, 0, &mut edits);
+    edits
+}
+
+fn diff_into<T: PartialEq>(
+    old: &[T],
+    new: &[T],
+    old_offset: usize,
+    new_offset: usize,
+    edits: &mut Vec<Edit>,
+) {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    for i in 0..prefix {
+        edits.push(Edit::Unchanged(old_offset + i, new_offset + i));
+    }
+
+    let old_mid = &old[prefix..old.len() - suffix];
+    let new_mid = &new[prefix..new.len() - suffix];
+    let (old_start, new_start) = (old_offset + prefix, new_offset + prefix);
+    if old_mid.is_empty() || new_mid.is_empty() {
+        edits.extend((0..old_mid.len()).map(|i| Edit::Deletion(old_start + i)));
+        edits.extend((0..new_mid.len()).map(|i| Edit::Insertion(new_start + i)));
+    } else if let Some((x, y)) = middle_point(old_mid, new_mid)
+        .filter(|&point| point != (0, 0) && point != (old_mid.len(), new_mid.len()))
+    {
+        diff_into(&old_mid[..x], &new_mid[..y], old_start, new_start, edits);
+        diff_into(
+            &old_mid[x..],
+            &new_mid[y..],
+            old_start + x,
+            new_start + y,
+            edits,
+        );
+    } else {
+        edits.extend((0..old_mid.len()).map(|i| Edit::Deletion(old_start + i)));
+        edits.extend((0..new_mid.len()).map(|i| Edit::Insertion(new_start + i)));
+    }
+
+    let (old_end, new_end) = (old_offset + old.len(), new_offset + new.len());
+    for i in (1..=suffix).rev() {
+        edits.push(Edit::Unchanged(old_end - i, new_end - i));
+    }
+}
+
+/// Finds a point on a shortest edit path between two non-empty sequences
+/// that differ at both ends, by running the forward and the backward search
+/// until they overlap. `forward[k]` (`backward[k]`) is the furthest x
+/// reached from the start (end) on diagonal `k`; diagonals that run off the
+/// edit graph are dropped from the search.
+fn middle_point<T: PartialEq>(old: &[T], new: &[T]) -> Option<(usize, usize)> {
+    let (n, m) = (old.len() as isize, new.len() as isize);
+    let max_d = (n + m + 1) / 2;
+    let offset = max_d;
+    let size = (2 * max_d + 2) as usize;
+    let mut forward = vec![-1isize; size];
+    let mut backward = vec![-1isize; size];
+    forward[(offset + 1) as usize] = 0;
+    backward[(offset + 1) as usize] = 0;
+    let delta = n - m;
+    // With an odd delta the paths meet during a forward step.
+    let front = delta % 2 != 0;
+    let (mut k1_start, mut k1_end, mut k2_start, mut k2_end) = (0, 0, 0, 0);
+
+    for d in 0..max_d {
+        for k1 in (-d + k1_start..=d - k1_end).step_by(2) {
+            let at = (offset + k1) as usize;
+            let mut x1 = if k1 == -d || (k1 != d && forward[at - 1] < forward[at + 1]) {
+                forward[at + 1]
+            } else {
+                forward[at - 1] + 1
+            };
+            let mut y1 = x1 - k1;
+            while x1 < n && y1 < m && old[x1 as usize] == new[y1 as usize] {
+                x1 += 1;
+                y1 += 1;
+            }
+            forward[at] = x1;
+            if x1 > n {
+                k1_end += 2;
+            } else if y1 > m {
+                k1_start += 2;
+            } else if front {
+                let k2 = offset + delta - k1;
+                if (0..size as isize).contains(&k2)
+                    && backward[k2 as usize] != -1
+                    && x1 >= n - backward[k2 as usize]
+                {
+                    return Some((x1 as usize, y1 as usize));
+                }
+            }
+        }
+
+        for k2 in (-d + k2_start..=d - k2_end).step_by(2) {
+            let at = (offset + k2) as usize;
+            let mut x2 = if k2 == -d || (k2 != d && backward[at - 1] < backward[at + 1]) {
+                backward[at + 1]
+            } else {
+                backward[at - 1] + 1
+            };
+            let mut y2 = x2 - k2;
+            while x2 < n && y2 < m && old[(n - x2 - 1) as usize] == new[(m - y2 - 1) as usize] {
+                x2 += 1;
+                y2 += 1;
+            }
+            backward[at] = x2;
+            if x2 > n {
+                k2_end += 2;
+            } else if y2 > m {
+                k2_start += 2;
+            } else if !front {
+                let k1 = offset + delta - k2;
+                if (0..size as isize).contains(&k1) && forward[k1 as usize] != -1 {
+                    let x1 = forward[k1 as usize];
+                    let y1 = offset + x1 - k1;
+                    if x1 >= n - x2 {
+                        return Some((x1 as usize, y1 as usize));
+                    }
+                }
+            }
+        }
+    }
+    None
+}
@@ -0,0 +1,219 @@
+use crate::diff::{FileDiff, Hunk, Line, Patch};
+use crate::parser::{ParseError, strip_markdown_fences};
+use regex::Regex;
+use std::sync::LazyLock;
+
+static NORMAL_COMMAND_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(\d+)(?:,(\d+))?([acd])(\d+)(?:,(\d+))?$").expect("Invalid regex")
+});
+
+static ED_COMMAND_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^(\d+)(?:,(\d+))?([acd])$").expect("Invalid regex"));
+
+pub(crate) fn is_normal_command(line: &str) -> bool {
+    NORMAL_COMMAND_RE.is_match(line.trim_end())
+}
+
+pub(crate) fn is_ed_command(line: &str) -> bool {
+    ED_COMMAND_RE.is_match(line.trim_end())
+}
+
+/// Converts a `start[,end]` range from a normal diff or ed command into a
+/// unified-style `(start, count)` pair. For `a` (old side) and `d` (new side)
+/// the single number is the line *after* which the change happens, which is
+/// exactly the unified convention for empty ranges.
+fn to_range(start: usize, end: Option<usize>, empty: bool) -> (usize, usize) {
+    if empty {
+        (start, 0)
+    } else {
+        let end = end.unwrap_or(start);
+        (start, end.saturating_sub(start) + 1)
+    }
+}
+
+fn parse_number(
+    caps: &regex::Captures,
+    group: usize,
+    line_number: usize,
+    line: &str,
+) -> Result<Option<usize>, ParseError> {
+    caps.get(group)
+        .map(|m| m.as_str().parse::<usize>())
+        .transpose()
+        .map_err(|e| ParseError {
+            line_number,
+            line_content: line.to_string(),
+            message: format!("Invalid number in command: {e}"),
+        })
+}
+
+/// Extracts the file names from a `diff [options] OLD NEW` line as printed by
+/// `diff -r`.
+fn parse_diff_command_paths(line: &str) -> Option<(String, String)> {
+    let tokens: Vec<&str> = line.split_whitespace().collect();
+    if tokens.len() < 3 || tokens[0] != "diff" {
+        return None;
+    }
+    let new_file = tokens[tokens.len() - 1].to_string();
+    let old_file = tokens[tokens.len() - 2].to_string();
+    Some((old_file, new_file))
+}
+
+/// Parses the output of plain `diff` (the "normal" format) into a [`Patch`].
+///
+/// Normal diffs carry no context, so each hunk only contains removal and
+/// addition lines. The removals become the anchors used by the matcher, and
+/// the line numbers are kept so pure additions can still be placed.
+pub fn parse_normal_diff(input: &str) -> Result<Patch, ParseError> {
+    let mut patch = Patch::default();
+    let mut current = FileDiff::default();
+
+    for (idx, line) in strip_markdown_fences(input).into_iter().enumerate() {
+        let line_number = idx + 1;
+
+        if let Some((old_file, new_file)) = parse_diff_command_paths(line) {
+            if !current.hunks.is_empty() {
+                patch.diffs.push(std::mem::take(&mut current));
+            }
+            current.old_file = old_file;
+            current.new_file = new_file;
+            continue;
+        }
+
+        if let Some(caps) = NORMAL_COMMAND_RE.captures(line.trim_end()) {
+            let old_start = parse_number(&caps, 1, line_number, line)?.unwrap_or(0);
+            let old_end = parse_number(&caps, 2, line_number, line)?;
+            let new_start = parse_number(&caps, 4, line_number, line)?.unwrap_or(0);
+            let new_end = parse_number(&caps, 5, line_number, line)?;
+            let command = &caps[3];
+
+            let (old_start, old_lines) = to_range(old_start, old_end, command == "a");
+            let (new_start, new_lines) = to_range(new_start, new_end, command == "d");
+            current.hunks.push(Hunk {
+                old_start,
+                old_lines,
+                new_start,
+                new_lines,
+                ..Default::default()
+            });
+            continue;
+        }
+
+        let Some(hunk) = current.hunks.last_mut() else {
+            continue;
+        };
+        if let Some(text) = line.strip_prefix("< ").or(line.strip_prefix('<')) {
+            hunk.lines.push(Line::Removal(text.to_string()));
+        } else if let Some(text) = line.strip_prefix("> ").or(line.strip_prefix('>')) {
+            hunk.lines.push(Line::Addition(text.to_string()));
+        }
+    }
+
+    if !current.hunks.is_empty() {
+        patch.diffs.push(current);
+    }
+    Ok(patch)
+}
+
+/// Parses an ed script as produced by `diff -e` into a [`Patch`].
+///
+/// Ed scripts only carry line numbers and the new text, so the lines being
+/// changed or deleted are read from `original_lines` to give every hunk real
+/// removal lines. Commands are emitted bottom-up by `diff -e`; the resulting
+/// hunks are sorted top-down like a regular diff.
+pub fn parse_ed_script(input: &str, original_lines: &[String]) -> Result<Patch, ParseError> {
+    let mut hunks: Vec<Hunk> = Vec::new();
+    let mut in_text = false;
+
+    for (idx, line) in strip_markdown_fences(input).into_iter().enumerate() {
+        let line_number = idx + 1;
+
+        if in_text {
+            if line == "." {
+                in_text = false;
+            } else if let Some(hunk) = hunks.last_mut() {
+                hunk.lines.push(Line::Addition(line.to_string()));
+            }
+            continue;
+        }
+
+        if line.trim().is_empty() || line.trim() == "w" || line.trim() == "q" {
+            continue;
+        }
+
+        let Some(caps) = ED_COMMAND_RE.captures(line.trim_end()) else {
+            return Err(ParseError {
+                line_number,
+                line_content: line.to_string(),
+                message: "Unrecognized ed command".to_string(),
+            });
+        };
+        let start = parse_number(&caps, 1, line_number, line)?.unwrap_or(0);
+        let end = parse_number(&caps, 2, line_number, line)?;
+        let command = &caps[3];
+
+        if start == 0 && command != "a" {
+            return Err(ParseError {
+                line_number,
+                line_content: line.to_string(),
+                message: "Line numbers start at 1; only `a` accepts 0".to_string(),
+            });
+        }
+
+        let (old_start, old_lines) = to_range(start, end, command == "a");
+        if old_start + old_lines > original_lines.len() + 1 && old_lines > 0 {
+            return Err(ParseError {
+                line_number,
+                line_content: line.to_string(),
+                message: format!(
+                    "Command refers to line {} but the original file has {} lines",
+                    old_start + old_lines - 1,
+                    original_lines.len()
+                ),
+            });
+        }
+
+        let mut hunk = Hunk {
+            old_start,
+            old_lines,
+            ..Default::default()
+        };
+        if old_lines > 0 {
+            for text in &original_lines[old_start - 1..old_start - 1 + old_lines] {
+                hunk.lines.push(Line::Removal(text.clone()));
+            }
+        }
+        hunks.push(hunk);
+        in_text = command != "d";
+    }
+
+    hunks.sort_by_key(|h| h.old_start);
+
+    let mut offset: isize = 0;
+    for hunk in &mut hunks {
+        hunk.new_lines = hunk
+            .lines
+            .iter()
+            .filter(|l| matches!(l, Line::Addition(_)))
+            .count();
+        let base = if hunk.old_lines == 0 {
+            hunk.old_start + 1
+        } else {
+            hunk.old_start
+        };
+        hunk.new_start = (base as isize + offset).max(0) as usize;
+        if hunk.new_lines == 0 {
+            hunk.new_start = hunk.new_start.saturating_sub(1);
+        }
+        offset += hunk.new_lines as isize - hunk.old_lines as isize;
+    }
+
+    let mut patch = Patch::default();
+    if !hunks.is_empty() {
+        patch.diffs.push(FileDiff {
+            hunks,
+            ..Default::default()
+        });
+    }
+    Ok(patch)
+}
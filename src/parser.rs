@@ -1,5 +1,23 @@
+use crate::begin_patch;
 use crate::diff::{FileDiff, Hunk, Line, Patch};
+use crate::normal;
+use crate::paths;
+use crate::search_replace;
+use crate::whole_file;
 use regex::Regex;
+use std::sync::LazyLock;
+
+static HUNK_HEADER_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"@@ -(\d+)(?:,(\d+))? \+(\d+)(?:,(\d+))? @@").expect("Invalid regex")
+});
+
+/// Timestamps and `(revision N)`-style suffixes after a header path.
+static HEADER_SUFFIX_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?:\s+\d{4}-\d{2}-\d{2}[ T]\d{2}:\d{2}(?::\d{2}(?:\.\d+)?)?(?:\s*[+-]\d{4})?|\s+\([^)]*\))\s*$",
+    )
+    .expect("Invalid regex")
+});
 
 #[derive(Debug)]
 pub struct ParseError {
@@ -18,13 +36,11 @@ impl std::fmt::Display for ParseError {
     }
 }
 
-fn strip_markdown_fences(input: &str) -> Vec<&str> {
+pub(crate) fn strip_markdown_fences(input: &str) -> Vec<&str> {
     let lines: Vec<&str> = input.lines().collect();
-    let start_fence_patterns = ["```diff", "```patch", "```"];
 
-    let start_idx = lines
-        .iter()
-        .position(|line| start_fence_patterns.contains(&line.trim()));
+    // The opening fence may carry an info string: "```diff", "```diff a.txt".
+    let start_idx = lines.iter().position(|line| line.trim().starts_with("```"));
 
     if let Some(start) = start_idx {
         let start_idx = start + 1;
@@ -40,6 +56,69 @@ fn strip_markdown_fences(input: &str) -> Vec<&str> {
     }
 }
 
+/// The diff dialects Mend knows how to read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffFormat {
+    /// `diff -u` / `git diff` output, including the messy LLM variants.
+    Unified,
+    /// Plain `diff` output (`12c12`, `< old`, `---`, `> new`).
+    Normal,
+    /// `diff -e` ed scripts.
+    Ed,
+    /// The `*** Begin Patch` / `*** End Patch` envelope used by OpenAI agents.
+    BeginPatch,
+    /// Aider-style `<<<<<<< SEARCH` / `>>>>>>> REPLACE` blocks.
+    SearchReplace,
+    /// Fenced code blocks holding a whole file, with a path hint.
+    WholeFile,
+}
+
+/// Guesses the format of a diff from its content. Anything with unified
+/// markers wins, since the unified parser is the most forgiving one.
+pub fn detect_format(input: &str) -> DiffFormat {
+    let lines = strip_markdown_fences(input);
+
+    if lines.iter().any(|line| begin_patch::is_begin_patch(line)) {
+        return DiffFormat::BeginPatch;
+    }
+
+    if search_replace::is_search_replace(input) {
+        return DiffFormat::SearchReplace;
+    }
+
+    let has_unified_markers = lines.iter().any(|line| {
+        let trimmed = line.trim_start();
+        trimmed.starts_with("@@") || trimmed.starts_with("+++") || trimmed.starts_with("diff --git")
+    });
+    if has_unified_markers {
+        return DiffFormat::Unified;
+    }
+
+    // Diff syntax wins over a fence with a path, which would otherwise
+    // replace the file with the diff text.
+    if lines.iter().any(|line| normal::is_normal_command(line))
+        && lines
+            .iter()
+            .any(|line| line.starts_with('<') || line.starts_with('>'))
+    {
+        return DiffFormat::Normal;
+    }
+
+    if lines
+        .iter()
+        .find(|line| !line.trim().is_empty())
+        .is_some_and(|line| normal::is_ed_command(line))
+    {
+        return DiffFormat::Ed;
+    }
+
+    if whole_file::is_whole_file_input(input) {
+        return DiffFormat::WholeFile;
+    }
+
+    DiffFormat::Unified
+}
+
 fn sanitize_diff(input: &str) -> String {
     let lines = strip_markdown_fences(input);
 
@@ -59,12 +138,17 @@ fn sanitize_diff(input: &str) -> String {
             result.push(line.to_string());
             if trimmed.starts_with("@@") {
                 in_hunk = true;
+            } else if trimmed.starts_with("diff --git") {
+                // The next file's metadata lines are not hunk content.
+                in_hunk = false;
             }
             continue;
         }
 
         if in_hunk {
-            if !line.is_empty()
+            if line.starts_with("\\ ") {
+                result.push(line.to_string());
+            } else if !line.is_empty()
                 && !line.starts_with('+')
                 && !line.starts_with('-')
                 && !line.starts_with(' ')
@@ -99,6 +183,8 @@ const GIT_METADATA_PREFIXES: &[&str] = &[
     "similarity index ",
     "rename from ",
     "rename to ",
+    "old mode ",
+    "new mode ",
     "Binary files ",
     "\\ No newline at end of file",
 ];
@@ -109,33 +195,142 @@ fn is_git_metadata(line: &str) -> bool {
         .any(|prefix| line.starts_with(prefix))
 }
 
-fn parse_diff_path(stripped: &str, prefix: &str) -> String {
-    let path_part = stripped.trim();
-    let path_candidate = if path_part.contains(char::is_whitespace) {
-        path_part.split_whitespace().last().unwrap_or(path_part)
-    } else {
-        path_part
-    };
-    let final_path = path_candidate
-        .strip_prefix(prefix)
-        .unwrap_or(path_candidate);
-    if final_path == "/dev/null" || final_path == "dev/null" {
-        "/dev/null".to_string()
-    } else {
-        final_path.to_string()
+/// Decodes a git C-style quoted path (`"caf\303\251 menu.txt"`), returning the
+/// path and whatever follows the closing quote.
+fn unquote_c_path(quoted: &str) -> Option<(String, &str)> {
+    let body = quoted.strip_prefix('"')?;
+    let mut bytes = Vec::new();
+    let mut chars = body.char_indices();
+    while let Some((idx, c)) = chars.next() {
+        match c {
+            '"' => {
+                return Some((
+                    String::from_utf8_lossy(&bytes).into_owned(),
+                    &body[idx + 1..],
+                ));
+            }
+            '\\' => {
+                let (_, escaped) = chars.next()?;
+                match escaped {
+                    'a' => bytes.push(0x07),
+                    'b' => bytes.push(0x08),
+                    't' => bytes.push(b'\t'),
+                    'n' => bytes.push(b'\n'),
+                    'v' => bytes.push(0x0b),
+                    'f' => bytes.push(0x0c),
+                    'r' => bytes.push(b'\r'),
+                    '0'..='7' => {
+                        let mut value = escaped.to_digit(8)?;
+                        for _ in 0..2 {
+                            let (_, digit) = chars.next()?;
+                            value = value * 8 + digit.to_digit(8)?;
+                        }
+                        bytes.push(u8::try_from(value).ok()?);
+                    }
+                    other => {
+                        let mut buf = [0; 4];
+                        bytes.extend_from_slice(other.encode_utf8(&mut buf).as_bytes());
+                    }
+                }
+            }
+            other => {
+                let mut buf = [0; 4];
+                bytes.extend_from_slice(other.encode_utf8(&mut buf).as_bytes());
+            }
+        }
     }
+    None
+}
+
+/// How leading directories are removed from the paths in file headers.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StripLevel {
+    /// Remove git's `a/` and `b/` prefixes, and a leading `./`.
+    #[default]
+    GitPrefixes,
+    /// Remove exactly N leading path components, like `patch -pN`.
+    Components(usize),
+}
+
+fn strip_header_path(path: &str, prefix: &str, strip: StripLevel) -> String {
+    let unprefixed = path.strip_prefix(prefix).unwrap_or(path);
+    if unprefixed == "/dev/null" || unprefixed == "dev/null" {
+        return "/dev/null".to_string();
+    }
+    match strip {
+        StripLevel::GitPrefixes => unprefixed
+            .strip_prefix("./")
+            .unwrap_or(unprefixed)
+            .to_string(),
+        StripLevel::Components(count) => {
+            paths::strip_components(path, count).unwrap_or_else(|| path.to_string())
+        }
+    }
+}
+
+/// Extracts the path from the remainder of a `---` / `+++` line.
+///
+/// Handles git's C-quoted paths, the tab-separated timestamps written by
+/// `diff -u`, space-separated timestamps and `(revision N)`-style suffixes,
+/// and keeps spaces that are part of the path itself.
+fn parse_diff_path(stripped: &str) -> String {
+    let path_part = stripped.trim_start();
+    if let Some((path, _)) = unquote_c_path(path_part) {
+        return path;
+    }
+
+    let path_part = path_part.split('\t').next().unwrap_or(path_part).trim_end();
+    HEADER_SUFFIX_RE.replace(path_part, "").trim().to_string()
+}
+
+/// Reads the old and new paths from a `diff --git a/x b/x` line, used when
+/// the `---` / `+++` headers are missing.
+fn parse_git_header_paths(rest: &str) -> Option<(String, String)> {
+    let rest = rest.trim();
+    if let Some((old_path, remainder)) = unquote_c_path(rest) {
+        let remainder = remainder.trim_start();
+        let new_path = match unquote_c_path(remainder) {
+            Some((path, _)) => path,
+            None => remainder.to_string(),
+        };
+        return Some((old_path, new_path));
+    }
+
+    // Unquoted paths may contain spaces. When both sides name the same file,
+    // the split is the " b/" for which they agree.
+    let splits: Vec<usize> = rest.match_indices(" b/").map(|(idx, _)| idx).collect();
+    let split = splits
+        .iter()
+        .copied()
+        .find(|&idx| rest[..idx].strip_prefix("a/") == Some(&rest[idx + 3..]))
+        .or_else(|| splits.first().copied())?;
+    let (old_path, new_path) = (&rest[..split], rest[split + 1..].trim_end());
+    let new_path = match unquote_c_path(new_path) {
+        Some((path, _)) => path,
+        None => new_path.to_string(),
+    };
+    Some((old_path.to_string(), new_path))
 }
 
 pub fn parse_patch(patch_content: &str) -> Result<Patch, ParseError> {
+    parse_patch_with_strip(patch_content, StripLevel::default())
+}
+
+/// Parses a unified diff, removing leading directories from the header paths
+/// as described by `strip`.
+pub fn parse_patch_with_strip(patch_content: &str, strip: StripLevel) -> Result<Patch, ParseError> {
     let sanitized = sanitize_diff(patch_content);
-    let hunk_header_re =
-        Regex::new(r"@@ -(\d+)(?:,(\d+))? \+(\d+)(?:,(\d+))? @@").expect("Invalid regex");
     let mut patch = Patch::default();
     let mut current_file_diff: Option<FileDiff> = None;
 
     let mut save_current_diff = |diff: Option<FileDiff>| {
+        // Sections without hunks are kept when their metadata says what to
+        // do, e.g. a pure rename or an empty new file.
         if let Some(mut d) = diff
-            && !d.hunks.is_empty()
+            && (!d.hunks.is_empty()
+                || d.is_rename
+                || d.old_file == "/dev/null"
+                || d.new_file == "/dev/null")
         {
             if !d.old_file.is_empty() && d.old_file != "/dev/null" && d.new_file.is_empty() {
                 d.new_file = "/dev/null".to_string();
@@ -144,29 +339,53 @@ pub fn parse_patch(patch_content: &str) -> Result<Patch, ParseError> {
         }
     };
 
-    for (line_number, raw_line) in sanitized.lines().enumerate() {
-        let line = raw_line;
+    let lines: Vec<&str> = sanitized.lines().collect();
+    let mut after_old_header = false;
+    for (line_number, raw_line) in lines.iter().enumerate() {
+        let line = *raw_line;
+        let follows_old_header = std::mem::take(&mut after_old_header);
+        let has_hunks = current_file_diff
+            .as_ref()
+            .is_some_and(|d| !d.hunks.is_empty());
 
-        if line.starts_with("diff --git ") {
+        if let Some(rest) = line.strip_prefix("diff --git ") {
             save_current_diff(current_file_diff.take());
-            current_file_diff = Some(FileDiff::default());
+            let mut diff = FileDiff::default();
+            if let Some((old_file, new_file)) = parse_git_header_paths(rest) {
+                diff.old_file = strip_header_path(&old_file, "a/", strip);
+                diff.new_file = strip_header_path(&new_file, "b/", strip);
+            }
+            current_file_diff = Some(diff);
             continue;
         }
 
-        if let Some(stripped) = line.strip_prefix("---") {
+        // Inside a hunk, `---` only starts the next file when `+++` follows;
+        // otherwise it is the removal of a line starting with `--`.
+        if let Some(stripped) = line.strip_prefix("---")
+            && (!has_hunks
+                || lines
+                    .get(line_number + 1)
+                    .is_some_and(|next| next.starts_with("+++")))
+        {
             if stripped.trim().is_empty() {
                 continue;
             }
+            if has_hunks {
+                save_current_diff(current_file_diff.take());
+            }
             if current_file_diff.is_none() {
                 current_file_diff = Some(FileDiff::default());
             }
+            after_old_header = true;
             if let Some(diff) = current_file_diff.as_mut() {
-                diff.old_file = parse_diff_path(stripped, "a/");
+                diff.old_file = strip_header_path(&parse_diff_path(stripped), "a/", strip);
             }
             continue;
         }
 
-        if let Some(stripped) = line.strip_prefix("+++") {
+        if let Some(stripped) = line.strip_prefix("+++")
+            && (!has_hunks || follows_old_header)
+        {
             if stripped.trim().is_empty() {
                 continue;
             }
@@ -174,7 +393,7 @@ pub fn parse_patch(patch_content: &str) -> Result<Patch, ParseError> {
                 current_file_diff = Some(FileDiff::default());
             }
             if let Some(diff) = current_file_diff.as_mut() {
-                diff.new_file = parse_diff_path(stripped, "b/");
+                diff.new_file = strip_header_path(&parse_diff_path(stripped), "b/", strip);
                 if diff.old_file.is_empty() {
                     diff.old_file = "/dev/null".to_string();
                 }
@@ -184,7 +403,7 @@ pub fn parse_patch(patch_content: &str) -> Result<Patch, ParseError> {
 
         if line.starts_with("@@") {
             let mut new_hunk = Hunk::default();
-            if let Some(caps) = hunk_header_re.captures(line) {
+            if let Some(caps) = HUNK_HEADER_RE.captures(line) {
                 let parse_num = |group: usize, default: usize| -> Result<usize, ParseError> {
                     caps.get(group)
                         .map_or(Ok(default), |m| m.as_str().parse::<usize>())
@@ -199,6 +418,12 @@ pub fn parse_patch(patch_content: &str) -> Result<Patch, ParseError> {
                 new_hunk.old_lines = parse_num(2, 1)?;
                 new_hunk.new_start = parse_num(3, 0)?;
                 new_hunk.new_lines = parse_num(4, 1)?;
+
+                // Git names the enclosing function after the header.
+                let section = line[caps.get(0).map_or(0, |m| m.end())..].trim();
+                if !section.is_empty() {
+                    new_hunk.section_hints.push(section.to_string());
+                }
             } else {
                 return Err(ParseError {
                     line_number: line_number + 1,
@@ -217,6 +442,70 @@ pub fn parse_patch(patch_content: &str) -> Result<Patch, ParseError> {
             continue;
         }
 
+        if let Some(blobs) = line.strip_prefix("index ")
+            && let Some(diff) = current_file_diff.as_mut()
+        {
+            let mut fields = blobs.split_whitespace();
+            if let Some((old_blob, new_blob)) = fields.next().and_then(|ids| ids.split_once("..")) {
+                diff.old_blob = Some(old_blob.to_string());
+                diff.new_blob = Some(new_blob.to_string());
+                // The mode of a file whose mode did not change.
+                if let Some(mode) = fields.next() {
+                    diff.old_mode.get_or_insert_with(|| mode.to_string());
+                    diff.new_mode.get_or_insert_with(|| mode.to_string());
+                }
+                continue;
+            }
+        }
+
+        if line.starts_with("\\ ") {
+            // `\ No newline at end of file` applies to the line above it.
+            if let Some(hunk) = current_file_diff
+                .as_mut()
+                .and_then(|diff| diff.hunks.last_mut())
+            {
+                match hunk.lines.last() {
+                    Some(Line::Context(_)) => {
+                        hunk.old_missing_newline = true;
+                        hunk.new_missing_newline = true;
+                    }
+                    Some(Line::Removal(_)) => hunk.old_missing_newline = true,
+                    Some(Line::Addition(_)) => hunk.new_missing_newline = true,
+                    None => {}
+                }
+            }
+            continue;
+        }
+
+        if let Some(diff) = current_file_diff.as_mut() {
+            let mode = |prefix: &str| line.strip_prefix(prefix).map(|m| m.trim().to_string());
+            if let Some(new_mode) = mode("new file mode ") {
+                diff.old_file = "/dev/null".to_string();
+                diff.new_mode = Some(new_mode);
+                continue;
+            }
+            if let Some(old_mode) = mode("deleted file mode ") {
+                diff.new_file = "/dev/null".to_string();
+                diff.old_mode = Some(old_mode);
+                continue;
+            }
+            if let Some(old_mode) = mode("old mode ") {
+                diff.old_mode = Some(old_mode);
+                continue;
+            }
+            if let Some(new_mode) = mode("new mode ") {
+                diff.new_mode = Some(new_mode);
+                continue;
+            }
+        }
+
+        if line.starts_with("rename from ") || line.starts_with("rename to ") {
+            if let Some(diff) = current_file_diff.as_mut() {
+                diff.is_rename = true;
+            }
+            continue;
+        }
+
         if is_git_metadata(line) {
             continue;
         }
@@ -1,4 +1,5 @@
 use crate::diff::{Hunk, Line};
+use crate::myers::{self, Edit};
 use lcs::LcsTable;
 use std::cmp::Ordering;
 use std::collections::HashMap;
@@ -7,9 +8,38 @@ use std::time::Instant;
 
 #[derive(Debug)]
 pub enum FilePatchResult {
-    Modified { path: String, new_content: String },
-    Created { path: String, new_content: String },
-    Deleted { path: String },
+    Modified {
+        path: String,
+        new_content: String,
+    },
+    Created {
+        path: String,
+        new_content: String,
+    },
+    Deleted {
+        path: String,
+    },
+    Renamed {
+        old_path: String,
+        path: String,
+        new_content: String,
+    },
+}
+
+/// How a hunk was located in the file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum MatchStrategy {
+    /// The hunk has no context or removals and was placed by line number.
+    LineNumber,
+    /// Character-for-character match.
+    Exact,
+    /// Match after normalizing whitespace and skipping blank lines.
+    Whitespace,
+    /// Anchor-point heuristic, scored by similarity.
+    Fuzzy,
+    /// Merged with the base blob of a git diff (see [`crate::three_way`]).
+    ThreeWay,
 }
 
 #[derive(Debug, Clone)]
@@ -18,6 +48,7 @@ pub struct HunkMatch {
     pub matched_length: usize,
     pub score: f32,
     pub density: f32,
+    pub strategy: MatchStrategy,
 }
 
 #[derive(Debug)]
@@ -193,6 +224,7 @@ pub fn find_strict_match(
             matched_length: 0,
             score: 1.0,
             density: 1.0,
+            strategy: MatchStrategy::LineNumber,
         }];
     }
 
@@ -224,6 +256,7 @@ pub fn find_strict_match(
             matched_length: anchor_lines.len(),
             score: 1.0,
             density: 1.0,
+            strategy: MatchStrategy::Exact,
         }];
     }
 
@@ -238,6 +271,59 @@ pub fn find_strict_match(
     Vec::new()
 }
 
+/// Finds where `hunk` appears to be applied already: its new side, context
+/// and added lines together, is present verbatim in the file. Only hunks
+/// that add lines are checked, as leftover context alone says little.
+pub fn find_applied_match(source_lines: &[String], hunk: &Hunk) -> Option<HunkMatch> {
+    if !hunk
+        .lines
+        .iter()
+        .any(|line| matches!(line, Line::Addition(_)))
+    {
+        return None;
+    }
+    find_strict_match(source_lines, &hunk.invert(), 0, false)
+        .into_iter()
+        .next()
+}
+
+/// How to settle a hunk that matches several places without asking.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum AmbiguityPolicy {
+    /// Stop with an error.
+    Fail,
+    /// Take the match with the highest score.
+    Best,
+    /// Take the match closest to where the diff places the hunk.
+    Nearest,
+    /// Take the match highest up in the file.
+    First,
+}
+
+/// Picks one of several matches of a hunk by `policy`, or `None` when the
+/// policy is to fail. `expected` is the line index where the diff, shifted
+/// by the drift seen so far, places the hunk.
+pub fn pick_match(
+    matches: &[HunkMatch],
+    policy: AmbiguityPolicy,
+    expected: usize,
+) -> Option<HunkMatch> {
+    match policy {
+        AmbiguityPolicy::Fail => None,
+        // Ties go to the match higher up in the file.
+        AmbiguityPolicy::Best => matches.iter().max_by(|a, b| {
+            a.score
+                .total_cmp(&b.score)
+                .then(b.start_index.cmp(&a.start_index))
+        }),
+        AmbiguityPolicy::Nearest => matches
+            .iter()
+            .min_by_key(|m| (m.start_index.abs_diff(expected), m.start_index)),
+        AmbiguityPolicy::First => matches.iter().min_by_key(|m| m.start_index),
+    }
+    .cloned()
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct MatchOptions {
     pub fuzziness: u8,
@@ -268,6 +354,7 @@ pub fn find_fuzzy_match(
             matched_length: 0,
             score: 1.0,
             density: 1.0,
+            strategy: MatchStrategy::LineNumber,
         }];
     }
 
@@ -331,6 +418,7 @@ pub fn find_fuzzy_match(
                     matched_length,
                     score: 0.9,
                     density,
+                    strategy: MatchStrategy::Whitespace,
                 });
             }
         }
@@ -484,6 +572,7 @@ pub fn find_fuzzy_match(
                             matched_length: length,
                             score,
                             density,
+                            strategy: MatchStrategy::Fuzzy,
                         });
                     }
                 }
@@ -505,6 +594,45 @@ pub fn find_fuzzy_match(
     deduplicate_matches(matches)
 }
 
+/// Locates the scope named by a hunk's section hints (e.g. `class Foo`, then
+/// `def bar`), searching for each hint after the previous one. Returns the
+/// line of the innermost hint that could be found.
+pub fn find_section_start(source_lines: &[String], hints: &[String]) -> Option<usize> {
+    let mut section_start = None;
+    let mut from = 0;
+    for hint in hints {
+        let needle = normalize_line(hint);
+        if needle.is_empty() {
+            continue;
+        }
+        let Some(offset) = source_lines[from.min(source_lines.len())..]
+            .iter()
+            .position(|line| normalize_line(line).contains(&needle))
+        else {
+            break;
+        };
+        section_start = Some(from + offset);
+        from += offset + 1;
+    }
+    section_start
+}
+
+/// Keeps the first match located inside the hinted section. Matches are left
+/// untouched when none of them lies after the section start.
+pub fn narrow_by_section(matches: Vec<HunkMatch>, section_start: usize) -> Vec<HunkMatch> {
+    if matches.len() <= 1 {
+        return matches;
+    }
+    match matches
+        .iter()
+        .filter(|m| m.start_index >= section_start)
+        .min_by_key(|m| m.start_index)
+    {
+        Some(nearest) => vec![nearest.clone()],
+        None => matches,
+    }
+}
+
 pub fn find_hunk_location(
     source_lines: &[String],
     clean_source_map: &[(usize, String)],
@@ -559,6 +687,29 @@ fn calculate_match_score(clean_anchor: &[&str], candidate_block: &[String]) -> f
     lcs_len as f32 / clean_anchor.len() as f32
 }
 
+/// Computes the line-level edit turning `old` into `new`, comparing lines by
+/// `key`. Unchanged lines keep the text from `old`.
+pub fn diff_lines_by(old: &[String], new: &[String], key: impl Fn(&str) -> String) -> Vec<Line> {
+    let old_keys: Vec<String> = old.iter().map(|line| key(line)).collect();
+    let new_keys: Vec<String> = new.iter().map(|line| key(line)).collect();
+
+    let mut lines = Vec::new();
+    let mut pending_additions = Vec::new();
+    for edit in myers::diff(&old_keys, &new_keys) {
+        match edit {
+            Edit::Unchanged(a, _) => {
+                lines.append(&mut pending_additions);
+                lines.push(Line::Context(old[a].clone()));
+            }
+            Edit::Deletion(a) => lines.push(Line::Removal(old[a].clone())),
+            // Additions are held back so every change reads removals first.
+            Edit::Insertion(b) => pending_additions.push(Line::Addition(new[b].clone())),
+        }
+    }
+    lines.append(&mut pending_additions);
+    lines
+}
+
 pub fn apply_hunk(
     source_lines: &[String],
     hunk: &Hunk,
@@ -579,6 +730,41 @@ pub fn apply_hunk(
     result
 }
 
+/// Counts how many lines a deletion diff and the file being deleted have in
+/// common, in order and once normalized, out of the longer of the two.
+/// Blank lines are left out. Equal counts mean the diff removes exactly the
+/// file.
+pub fn deletion_match(removed_lines: &[String], source_lines: &[String]) -> (usize, usize) {
+    let normalize = |lines: &[String]| -> Vec<String> {
+        lines
+            .iter()
+            .map(|l| normalize_line(l))
+            .filter(|l| !l.is_empty())
+            .collect()
+    };
+    if removed_lines == source_lines {
+        let total = normalize(source_lines).len();
+        return (total, total);
+    }
+    let removed = normalize(removed_lines);
+    let source = normalize(source_lines);
+    let common = myers::diff(&removed, &source)
+        .iter()
+        .filter(|edit| matches!(edit, myers::Edit::Unchanged(..)))
+        .count();
+    (common, removed.len().max(source.len()))
+}
+
+/// The share of lines a deletion diff and the deleted file have in common,
+/// as counted by [`deletion_match`]. `1.0` means the diff removes exactly
+/// the file.
+pub fn deletion_match_score(removed_lines: &[String], source_lines: &[String]) -> f32 {
+    match deletion_match(removed_lines, source_lines) {
+        (_, 0) => 1.0,
+        (common, total) => common as f32 / total as f32,
+    }
+}
+
 pub fn normalize_line(line: &str) -> String {
     let mut result = String::with_capacity(line.len() * 2);
     let mut iter = line.chars().peekable();
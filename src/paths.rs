@@ -0,0 +1,232 @@
+use crate::diff::{FileDiff, Patch};
+use crate::error::AppError;
+use crate::parser::{self, StripLevel};
+use std::path::{Component, Path};
+
+/// Highest `-p` level tried when guessing how a patch was generated.
+const MAX_GUESSED_STRIP_LEVEL: usize = 4;
+
+/// Removes `count` leading components from `path`, like `patch -pN`.
+/// Repeated slashes count as one separator. Returns `None` when the path has
+/// no component left to keep.
+pub fn strip_components(path: &str, count: usize) -> Option<String> {
+    let mut rest = path;
+    for _ in 0..count {
+        let (_, tail) = rest.split_once('/')?;
+        rest = tail.trim_start_matches('/');
+    }
+    (!rest.is_empty()).then(|| rest.to_string())
+}
+
+/// Prefixes `path` with `directory`, leaving `/dev/null` and empty (unknown)
+/// paths alone.
+pub fn prepend_directory(directory: &str, path: &str) -> String {
+    if path.is_empty() || path == "/dev/null" {
+        return path.to_string();
+    }
+    Path::new(directory)
+        .join(path)
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Undoes [`prepend_directory`]: `path` relative to `directory`, or `path`
+/// itself when it is not inside `directory`.
+pub fn strip_directory(directory: &str, path: &str) -> String {
+    Path::new(path)
+        .strip_prefix(directory)
+        .ok()
+        .filter(|rest| !rest.as_os_str().is_empty())
+        .map_or_else(
+            || path.to_string(),
+            |rest| rest.to_string_lossy().into_owned(),
+        )
+}
+
+fn normal_components(path: &str) -> Vec<Component<'_>> {
+    Path::new(path)
+        .components()
+        .filter(|c| !matches!(c, Component::CurDir))
+        .collect()
+}
+
+/// Number of trailing components `diff_path` and `target` share, provided
+/// the shorter of the two is entirely a suffix of the longer one: `mod.rs`
+/// and `a/mod.rs` both match `src/a/mod.rs`, but `b/mod.rs` does not.
+pub fn suffix_match_len(diff_path: &str, target: &str) -> Option<usize> {
+    let diff_components = normal_components(diff_path);
+    let target_components = normal_components(target);
+    let shorter = diff_components.len().min(target_components.len());
+    let shared = diff_components
+        .iter()
+        .rev()
+        .zip(target_components.iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    (shorter > 0 && shared == shorter).then_some(shared)
+}
+
+/// Keeps the diffs of `patch` whose path best matches `target`: among the
+/// paths `target` is a suffix of (or that are a suffix of it), those sharing
+/// the most trailing components. Sections without any path are kept.
+///
+/// Fails when nothing matches, or when the best matches name several files.
+pub fn retain_target(patch: &mut Patch, target: &str) -> Result<(), AppError> {
+    // The path of `diff` that corresponds to the target, and how many
+    // trailing components they share.
+    let target_match = |diff: &FileDiff| -> Option<(usize, String)> {
+        [&diff.new_file, &diff.old_file]
+            .into_iter()
+            .filter(|path| path.as_str() != "/dev/null")
+            .filter_map(|path| suffix_match_len(path, target).map(|len| (len, path.clone())))
+            .max_by_key(|(len, _)| *len)
+    };
+    let best = patch
+        .diffs
+        .iter()
+        .filter_map(|diff| target_match(diff).map(|(len, _)| len))
+        .max();
+
+    let mut matched_paths: Vec<String> = Vec::new();
+    patch.diffs.retain(|diff| {
+        if diff.old_file.is_empty() && diff.new_file.is_empty() {
+            return true;
+        }
+        match target_match(diff) {
+            Some((len, path)) if Some(len) == best => {
+                matched_paths.push(path);
+                true
+            }
+            _ => false,
+        }
+    });
+
+    if patch.diffs.is_empty() {
+        return Err(AppError::NoMatchingChanges {
+            target_file: target.to_string(),
+        });
+    }
+    matched_paths.sort();
+    matched_paths.dedup();
+    if matched_paths.len() > 1 {
+        return Err(AppError::AmbiguousTarget {
+            target_file: target.to_string(),
+            paths: matched_paths,
+        });
+    }
+    Ok(())
+}
+
+/// A free path next to `path` to write new content to: `src/x.rs` becomes
+/// `src/x.new.rs`, then `src/x.new2.rs`, and so on.
+pub fn alternate_path(path: &str) -> String {
+    let path = Path::new(path);
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let extension = path
+        .extension()
+        .map(|e| format!(".{}", e.to_string_lossy()))
+        .unwrap_or_default();
+    (1..)
+        .map(|n| {
+            let suffix = if n == 1 { String::new() } else { n.to_string() };
+            path.with_file_name(format!("{stem}.new{suffix}{extension}"))
+        })
+        .find(|candidate| !candidate.exists())
+        .map(|candidate| candidate.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// Counts the files a patch modifies that exist under `base`.
+fn count_existing_targets(patch: &crate::diff::Patch, base: &Path) -> usize {
+    patch
+        .diffs
+        .iter()
+        .filter(|d| !d.old_file.is_empty() && d.old_file != "/dev/null")
+        .filter(|d| base.join(&d.old_file).is_file())
+        .count()
+}
+
+/// Guesses the `-p` level for a unified diff whose header paths do not
+/// resolve under `base` as written, e.g. a diff made from a parent directory
+/// or by `svn`.
+///
+/// Each level up to [`MAX_GUESSED_STRIP_LEVEL`] is tried, and the one that
+/// finds the most existing files wins, the lowest level breaking ties.
+/// Returns `None` when the default `a/` / `b/` handling finds at least as many
+/// files.
+pub fn guess_strip_level(patch_content: &str, base: &Path) -> Option<usize> {
+    let default_patch = parser::parse_patch(patch_content).ok()?;
+    let wanted = default_patch
+        .diffs
+        .iter()
+        .filter(|d| !d.old_file.is_empty() && d.old_file != "/dev/null")
+        .count();
+    let default_hits = count_existing_targets(&default_patch, base);
+    if default_hits == wanted {
+        return None;
+    }
+
+    let mut best: Option<(usize, usize)> = None;
+    for level in 0..=MAX_GUESSED_STRIP_LEVEL {
+        let Ok(patch) =
+            parser::parse_patch_with_strip(patch_content, StripLevel::Components(level))
+        else {
+            continue;
+        };
+        let hits = count_existing_targets(&patch, base);
+        if hits > best.map_or(default_hits, |(_, best_hits)| best_hits) {
+            best = Some((level, hits));
+        }
+    }
+    best.map(|(level, _)| level)
+}
+
+/// Checks that `path`, relative to `root`, stays inside `root` once
+/// symlinks are resolved. `directory` is the `--directory`
+/// prefix given by the user, which is exempt from the checks on the path
+/// as written in the diff: no absolute paths and no `..` components.
+pub fn validate_diff_path(root: &Path, directory: Option<&str>, path: &str) -> Result<(), String> {
+    let as_written = directory
+        .and_then(|dir| Path::new(path).strip_prefix(dir).ok())
+        .unwrap_or(Path::new(path));
+    if as_written.has_root() || as_written.is_absolute() {
+        return Err("absolute paths are not allowed".to_string());
+    }
+    if as_written
+        .components()
+        .any(|c| matches!(c, Component::ParentDir))
+    {
+        return Err("'..' components are not allowed".to_string());
+    }
+
+    let canonical_root = root
+        .canonicalize()
+        .map_err(|e| format!("cannot resolve the root {}: {e}", root.display()))?;
+    let full = root.join(path);
+
+    // Resolve the deepest part of the path that exists, following symlinks;
+    // the rest is created as plain directories and files.
+    let mut existing = full.as_path();
+    while existing.symlink_metadata().is_err() {
+        existing = existing
+            .parent()
+            .ok_or_else(|| "the path has no existing parent".to_string())?;
+    }
+    let resolved = existing
+        .canonicalize()
+        .map_err(|_| "it is a symlink to a missing file".to_string())?;
+    let remainder = full.strip_prefix(existing).unwrap_or(Path::new(""));
+    if remainder
+        .components()
+        .any(|c| matches!(c, Component::ParentDir))
+    {
+        return Err("'..' components are not allowed".to_string());
+    }
+    if !resolved.join(remainder).starts_with(&canonical_root) {
+        return Err(format!(
+            "it resolves outside the root directory {}",
+            canonical_root.display()
+        ));
+    }
+    Ok(())
+}
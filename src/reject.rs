@@ -0,0 +1,8 @@
+use crate::diff::Hunk;
+use crate::unified;
+
+/// Formats the hunks that could not be applied to a file as a unified diff,
+/// the content of its `.rej` file. See [`unified::format_hunks`].
+pub fn format_reject_file(old_path: &str, new_path: &str, hunks: &[Hunk]) -> String {
+    unified::format_hunks(old_path, new_path, hunks)
+}
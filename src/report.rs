@@ -0,0 +1,230 @@
+use crate::decisions::Decision;
+use crate::diff::Hunk;
+use crate::error;
+use crate::patcher::{HunkMatch, MatchStrategy};
+use serde::Serialize;
+
+/// A stable name for each kind of warning, for tools reading the JSON report.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum WarningCode {
+    FuzzyMatch,
+    ResolvedPath,
+    UncheckedDeletion,
+    PartialDeletion,
+    OverwroteExisting,
+    AlternatePath,
+    SkippedBinary,
+    RejectsWritten,
+    AlreadyApplied,
+    AmbiguityResolved,
+    SkippedHunk,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Warning {
+    pub code: WarningCode,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum HunkStatus {
+    Applied,
+    Skipped,
+    Failed,
+    AlreadyApplied,
+    Conflict,
+}
+
+/// What happened to one hunk of a file section.
+#[derive(Debug, Serialize)]
+pub struct HunkRecord {
+    /// 1-based position of the hunk in its section.
+    pub index: usize,
+    pub status: HunkStatus,
+    /// 1-based line where the hunk was placed.
+    pub line: Option<usize>,
+    pub strategy: Option<MatchStrategy>,
+    pub score: Option<f32>,
+    pub density: Option<f32>,
+    /// How far `line` is from the line number given by the diff.
+    pub offset: Option<isize>,
+}
+
+impl HunkRecord {
+    pub fn placed(hunk_index: usize, status: HunkStatus, hunk: &Hunk, m: &HunkMatch) -> Self {
+        let line = m.start_index + 1;
+        HunkRecord {
+            index: hunk_index + 1,
+            status,
+            line: Some(line),
+            strategy: Some(m.strategy),
+            score: Some(m.score),
+            density: Some(m.density),
+            offset: (hunk.old_start > 0).then(|| line as isize - hunk.old_start as isize),
+        }
+    }
+
+    pub fn unplaced(hunk_index: usize, status: HunkStatus) -> Self {
+        HunkRecord {
+            index: hunk_index + 1,
+            status,
+            line: None,
+            strategy: None,
+            score: None,
+            density: None,
+            offset: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FileStatus {
+    Modified,
+    Created,
+    Deleted,
+    Renamed,
+    Skipped,
+    Failed,
+}
+
+/// What happened to one file section of the diff.
+#[derive(Debug, Serialize)]
+pub struct FileRecord {
+    pub path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub old_path: Option<String>,
+    pub status: FileStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reject_file: Option<String>,
+    pub hunks: Vec<HunkRecord>,
+}
+
+#[derive(Default, Debug, Serialize)]
+pub struct Report {
+    pub files_modified: usize,
+    pub files_created: usize,
+    pub files_deleted: usize,
+    pub files_renamed: usize,
+    pub hunks_applied: usize,
+    pub hunks_skipped: usize,
+    pub warnings: Vec<Warning>,
+    /// `.rej` files to write, with their content.
+    #[serde(skip)]
+    pub reject_files: Vec<(String, String)>,
+    /// Choices made at prompts, saved by `--record`.
+    #[serde(skip)]
+    pub decisions: Vec<Decision>,
+    /// Files left with conflict markers.
+    pub conflict_files: Vec<String>,
+    /// One record per file section, in diff order.
+    pub files: Vec<FileRecord>,
+    pub elapsed_ms: Option<u128>,
+}
+
+impl Report {
+    pub fn warn(&mut self, code: WarningCode, message: String) {
+        self.warnings.push(Warning { code, message });
+    }
+
+    /// Records the outcome of a hunk in the current file and counts it.
+    pub fn record_hunk(&mut self, record: HunkRecord) {
+        match record.status {
+            HunkStatus::Applied => self.hunks_applied += 1,
+            HunkStatus::Failed => {}
+            _ => self.hunks_skipped += 1,
+        }
+        if let Some(file) = self.files.last_mut() {
+            file.hunks.push(record);
+        }
+    }
+
+    /// The exit code of a run that finished without error: partial when
+    /// files or hunks were skipped or hunks found already applied, and with
+    /// `--fail-on-warning`, failing on any warning.
+    pub fn exit_code(&self, fail_on_warning: bool) -> i32 {
+        let skipped = self.files.iter().any(|file| {
+            file.status == FileStatus::Skipped
+                || file.hunks.iter().any(|hunk| {
+                    matches!(
+                        hunk.status,
+                        HunkStatus::Skipped | HunkStatus::AlreadyApplied
+                    )
+                })
+        });
+        if skipped {
+            error::EXIT_PARTIAL
+        } else if fail_on_warning && !self.warnings.is_empty() {
+            error::EXIT_WARNINGS
+        } else {
+            error::EXIT_SUCCESS
+        }
+    }
+
+    pub fn summary(&self, dry_run: bool, revert: bool) -> String {
+        let action = if revert { "reverted" } else { "applied" };
+        let time_str = self
+            .elapsed_ms
+            .map(|ms| {
+                if ms < 1000 {
+                    format!(" in {ms}ms")
+                } else {
+                    format!(" in {:.2}s", (ms as f64) / 1000.0)
+                }
+            })
+            .unwrap_or_default();
+        let header = if dry_run {
+            format!("\nSummary{time_str}")
+        } else if self.warnings.is_empty() {
+            format!("✔ Patch {action} successfully{time_str}")
+        } else {
+            format!("✔ Patch {action} with warnings{time_str}")
+        };
+
+        let mut file_parts = Vec::new();
+        if self.files_created > 0 {
+            file_parts.push(format!("{} created", self.files_created));
+        }
+        if self.files_modified > 0 {
+            file_parts.push(format!("{} modified", self.files_modified));
+        }
+        if self.files_deleted > 0 {
+            file_parts.push(format!("{} deleted", self.files_deleted));
+        }
+        if self.files_renamed > 0 {
+            file_parts.push(format!("{} renamed", self.files_renamed));
+        }
+
+        let mut hunk_parts = Vec::new();
+        let hunk_text = if self.hunks_applied == 1 {
+            "hunk"
+        } else {
+            "hunks"
+        };
+        hunk_parts.push(format!("{} {} {}", self.hunks_applied, hunk_text, action));
+        if self.hunks_skipped > 0 {
+            hunk_parts.push(format!("{} skipped", self.hunks_skipped));
+        }
+
+        let mut summary_parts = Vec::new();
+        if !file_parts.is_empty() {
+            summary_parts.push(file_parts.join(", "));
+        }
+        summary_parts.push(hunk_parts.join(", "));
+
+        let summary_line = summary_parts.join(" | ");
+
+        let mut final_string = format!("{header}: {summary_line}");
+
+        if !self.warnings.is_empty() {
+            final_string.push_str("\n\n--- Warnings ---");
+            for warning in &self.warnings {
+                final_string.push_str(&format!("\n- {}", warning.message));
+            }
+        }
+
+        final_string
+    }
+}
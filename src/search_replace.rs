@@ -0,0 +1,145 @@
+use crate::diff::{FileDiff, Hunk, Line, Patch};
+use crate::parser::ParseError;
+use crate::whole_file;
+use regex::Regex;
+use std::sync::LazyLock;
+
+static SEARCH_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^\s*<{5,9} SEARCH\s*$").expect("Invalid regex"));
+
+static DIVIDER_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^\s*={5,9}\s*$").expect("Invalid regex"));
+
+static REPLACE_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^\s*>{5,9} REPLACE\s*$").expect("Invalid regex"));
+
+pub(crate) fn is_search_replace(input: &str) -> bool {
+    input.lines().any(|line| SEARCH_RE.is_match(line))
+        && input.lines().any(|line| REPLACE_RE.is_match(line))
+}
+
+/// Extracts a file path from the line preceding a block, tolerating the
+/// decorations LLMs like to add (`**src/x.py**`, `` `src/x.py` ``, `File: x`).
+/// Only path-like tokens count, so a prose line such as `Then:` is not a
+/// filename.
+fn parse_filename_line(line: &str) -> Option<String> {
+    let trimmed = line.trim();
+    let trimmed = trimmed
+        .strip_prefix("File:")
+        .or_else(|| trimmed.strip_prefix("file:"))
+        .unwrap_or(trimmed)
+        .trim()
+        .trim_matches(|c| c == '`' || c == '*' || c == '"' || c == '\'')
+        .trim_end_matches(':')
+        .trim();
+    if trimmed.starts_with('#') || !whole_file::looks_like_path(trimmed) {
+        return None;
+    }
+    Some(trimmed.to_string())
+}
+
+/// Finds the path for the block starting at `search_idx` by walking back over
+/// blank lines and an opening code fence.
+fn find_block_filename(lines: &[&str], search_idx: usize) -> Option<String> {
+    let mut idx = search_idx;
+    while idx > 0 {
+        idx -= 1;
+        let trimmed = lines[idx].trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+        if let Some(info) = trimmed.strip_prefix("```") {
+            // A fence may carry the path itself, e.g. "```python src/x.py".
+            if let Some(path) = info.split_whitespace().nth(1) {
+                return parse_filename_line(path);
+            }
+            continue;
+        }
+        return parse_filename_line(trimmed);
+    }
+    None
+}
+
+enum State {
+    Outside,
+    Search,
+    Replace,
+}
+
+/// Parses aider-style `<<<<<<< SEARCH` / `=======` / `>>>>>>> REPLACE` blocks
+/// into a [`Patch`].
+///
+/// Every block becomes a hunk whose removals are the SEARCH text and whose
+/// additions are the REPLACE text. Blocks without a filename line reuse the
+/// previous one, and a block with an empty SEARCH section creates the file.
+pub fn parse_search_replace(input: &str) -> Result<Patch, ParseError> {
+    let lines: Vec<&str> = input.lines().collect();
+    let mut patch = Patch::default();
+    let mut state = State::Outside;
+    let mut current_file = String::new();
+    let mut hunk = Hunk::default();
+    let mut block_start = 0;
+
+    for (idx, line) in lines.iter().enumerate() {
+        match state {
+            State::Outside => {
+                if SEARCH_RE.is_match(line) {
+                    if let Some(name) = find_block_filename(&lines, idx) {
+                        current_file = name;
+                    }
+                    hunk = Hunk::default();
+                    block_start = idx;
+                    state = State::Search;
+                }
+            }
+            State::Search => {
+                if DIVIDER_RE.is_match(line) {
+                    state = State::Replace;
+                } else {
+                    hunk.lines.push(Line::Removal(line.to_string()));
+                }
+            }
+            State::Replace => {
+                if REPLACE_RE.is_match(line) {
+                    push_block(&mut patch, &current_file, std::mem::take(&mut hunk));
+                    state = State::Outside;
+                } else {
+                    hunk.lines.push(Line::Addition(line.to_string()));
+                }
+            }
+        }
+    }
+
+    if !matches!(state, State::Outside) {
+        return Err(ParseError {
+            line_number: block_start + 1,
+            line_content: lines[block_start].to_string(),
+            message: "SEARCH block is not terminated by '>>>>>>> REPLACE'".to_string(),
+        });
+    }
+
+    Ok(patch)
+}
+
+fn push_block(patch: &mut Patch, file: &str, mut hunk: Hunk) {
+    hunk.old_lines = hunk.anchor_len();
+    hunk.new_lines = hunk.lines.len() - hunk.old_lines;
+    let creates_file = hunk.old_lines == 0;
+
+    if !creates_file && let Some(diff) = patch.diffs.iter_mut().find(|d| d.new_file == file) {
+        diff.hunks.push(hunk);
+        return;
+    }
+
+    let old_file = if creates_file && !file.is_empty() {
+        "/dev/null".to_string()
+    } else {
+        file.to_string()
+    };
+    patch.diffs.push(FileDiff {
+        old_file,
+        new_file: file.to_string(),
+        hunks: vec![hunk],
+        ..Default::default()
+    });
+}
@@ -0,0 +1,143 @@
+use crate::diff::{FileDiff, Line};
+use crate::myers::{self, Edit};
+use crate::patcher;
+use std::path::Path;
+use std::process::Command;
+
+/// The outcome of a three-way merge.
+#[derive(Debug)]
+pub struct MergeResult {
+    /// The merged lines, with git-style conflict blocks where both sides
+    /// changed the same region differently.
+    pub lines: Vec<String>,
+    pub conflicts: usize,
+}
+
+/// Reads a blob from the git repository containing `repo`, e.g. the base
+/// version named by an `index abc123..def456` line. Returns `None` outside a
+/// repository, for unknown blobs, and for the all-zero id git uses for files
+/// that did not exist.
+pub fn read_git_blob(repo: &Path, blob_id: &str) -> Option<Vec<String>> {
+    if blob_id.is_empty() || blob_id.chars().all(|c| c == '0') {
+        return None;
+    }
+    if !blob_id.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let output = Command::new("git")
+        .args(["cat-file", "-p", blob_id])
+        .current_dir(repo)
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(String::from)
+            .collect(),
+    )
+}
+
+/// Applies `file_diff` to the exact file it was generated from, trusting its
+/// line numbers. Returns `None` as soon as a hunk does not match, which means
+/// `base` is not the diff's original after all.
+pub fn apply_to_base(base: &[String], file_diff: &FileDiff) -> Option<Vec<String>> {
+    let mut result = base.to_vec();
+    let mut delta: isize = 0;
+    for hunk in &file_diff.hunks {
+        let expected: Vec<&String> = hunk
+            .lines
+            .iter()
+            .filter_map(|line| match line {
+                Line::Context(text) | Line::Removal(text) => Some(text),
+                Line::Addition(_) => None,
+            })
+            .collect();
+        // An empty range names the line it follows.
+        let start = if expected.is_empty() {
+            hunk.old_start as isize + delta
+        } else {
+            hunk.old_start as isize - 1 + delta
+        };
+        let start = usize::try_from(start).ok()?;
+        let found = result.get(start..start + expected.len())?;
+        if found.iter().zip(&expected).any(|(a, b)| a != *b) {
+            return None;
+        }
+        let new_lines = hunk
+            .lines
+            .iter()
+            .filter(|l| !matches!(l, Line::Removal(_)))
+            .count();
+        result = patcher::apply_hunk(&result, hunk, start, expected.len());
+        delta += new_lines as isize - expected.len() as isize;
+    }
+    Some(result)
+}
+
+/// For each line of `base`, the index of the line of `other` it is matched
+/// with by a shortest edit script, if any.
+fn match_lines(base: &[String], other: &[String]) -> Vec<Option<usize>> {
+    let mut matches = vec![None; base.len()];
+    for edit in myers::diff(base, other) {
+        if let Edit::Unchanged(a, b) = edit {
+            matches[a] = Some(b);
+        }
+    }
+    matches
+}
+
+/// Merges the changes from `base` to `ours` and from `base` to `theirs`.
+///
+/// Regions changed on one side only take that side; regions changed the same
+/// way on both sides are taken once; anything else becomes a conflict block.
+pub fn merge3(base: &[String], ours: &[String], theirs: &[String]) -> MergeResult {
+    let ours_match = match_lines(base, ours);
+    let theirs_match = match_lines(base, theirs);
+
+    let mut lines = Vec::new();
+    let mut conflicts = 0;
+    let (mut o, mut a, mut b) = (0, 0, 0);
+
+    loop {
+        // The next base line kept in place on both sides closes the chunk.
+        let stable =
+            (o..base.len()).find(|&i| ours_match[i].is_some() && theirs_match[i].is_some());
+        let (o_end, a_end, b_end) = match stable {
+            Some(i) => (i, ours_match[i].unwrap_or(a), theirs_match[i].unwrap_or(b)),
+            None => (base.len(), ours.len(), theirs.len()),
+        };
+
+        let base_chunk = &base[o..o_end];
+        let ours_chunk = &ours[a..a_end];
+        let theirs_chunk = &theirs[b..b_end];
+        if ours_chunk == base_chunk || ours_chunk == theirs_chunk {
+            lines.extend_from_slice(theirs_chunk);
+        } else if theirs_chunk == base_chunk {
+            lines.extend_from_slice(ours_chunk);
+        } else {
+            conflicts += 1;
+            lines.push("<<<<<<< current".to_string());
+            lines.extend_from_slice(ours_chunk);
+            lines.push("||||||| base".to_string());
+            lines.extend_from_slice(base_chunk);
+            lines.push("=======".to_string());
+            lines.extend_from_slice(theirs_chunk);
+            lines.push(">>>>>>> diff".to_string());
+        }
+
+        match stable {
+            Some(i) => {
+                lines.push(base[i].clone());
+                o = i + 1;
+                a = a_end + 1;
+                b = b_end + 1;
+            }
+            None => break,
+        }
+    }
+
+    MergeResult { lines, conflicts }
+}
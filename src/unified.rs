@@ -0,0 +1,299 @@
+use crate::diff::{self, FileDiff, Hunk, Line, Patch};
+use crate::discovery;
+use crate::myers;
+use std::collections::BTreeSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Number of unchanged lines shown around each change, as with `diff -u`.
+pub const DEFAULT_CONTEXT: usize = 3;
+
+const BOLD: &str = "\x1b[1m";
+const CYAN: &str = "\x1b[36m";
+const GREEN: &str = "\x1b[32m";
+const RED: &str = "\x1b[31m";
+const RESET: &str = "\x1b[0m";
+
+/// Appended to the last line of a file without a trailing newline while
+/// diffing, so that adding or removing the newline changes that line.
+const MISSING_NEWLINE: char = '\0';
+
+/// Splits the line-by-line diff of a whole file into hunks keeping `context`
+/// unchanged lines around each change, with their line numbers. Changes
+/// closer than twice the context share a hunk.
+pub fn group_hunks(lines: &[Line], context: usize) -> Vec<Hunk> {
+    let changes: Vec<usize> = lines
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| !matches!(line, Line::Context(_)))
+        .map(|(idx, _)| idx)
+        .collect();
+
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    for &idx in &changes {
+        let start = idx.saturating_sub(context);
+        let end = (idx + context + 1).min(lines.len());
+        match ranges.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => ranges.push((start, end)),
+        }
+    }
+
+    ranges
+        .into_iter()
+        .map(|(start, end)| {
+            let before = &lines[..start];
+            let old_before = before
+                .iter()
+                .filter(|l| !matches!(l, Line::Addition(_)))
+                .count();
+            let new_before = before
+                .iter()
+                .filter(|l| !matches!(l, Line::Removal(_)))
+                .count();
+            let hunk_lines = lines[start..end].to_vec();
+            let old_lines = hunk_lines
+                .iter()
+                .filter(|l| !matches!(l, Line::Addition(_)))
+                .count();
+            let new_lines = hunk_lines
+                .iter()
+                .filter(|l| !matches!(l, Line::Removal(_)))
+                .count();
+            // An empty range names the line it follows.
+            Hunk {
+                old_start: old_before + usize::from(old_lines > 0),
+                old_lines,
+                new_start: new_before + usize::from(new_lines > 0),
+                new_lines,
+                lines: hunk_lines,
+                ..Default::default()
+            }
+        })
+        .collect()
+}
+
+/// Formats `hunks` as a unified diff from `old_path` to `new_path`, without
+/// the `diff --git` line. See [`diff::write_unified_hunks`].
+pub fn format_hunks(old_path: &str, new_path: &str, hunks: &[Hunk]) -> String {
+    let mut out = String::new();
+    diff::write_unified_hunks(&mut out, old_path, new_path, hunks)
+        .expect("writing to a String cannot fail");
+    out
+}
+
+fn split_content(content: &str) -> Vec<String> {
+    let mut lines: Vec<String> = content.lines().map(String::from).collect();
+    if !content.ends_with('\n')
+        && let Some(last) = lines.last_mut()
+    {
+        last.push(MISSING_NEWLINE);
+    }
+    lines
+}
+
+/// Diffs `old`, the content of `old_path`, against `new`, the content of
+/// `new_path`, keeping `context` unchanged lines around each change. A
+/// missing newline at the end of either side is recorded on the last hunk.
+pub fn diff_contents(
+    old_path: &str,
+    new_path: &str,
+    old: &str,
+    new: &str,
+    context: usize,
+) -> FileDiff {
+    let lines = myers::diff_lines(&split_content(old), &split_content(new));
+    let mut hunks = group_hunks(&lines, context);
+    for hunk in &mut hunks {
+        for line in &mut hunk.lines {
+            let (text, old_side, new_side) = match line {
+                Line::Context(text) => (text, true, true),
+                Line::Removal(text) => (text, true, false),
+                Line::Addition(text) => (text, false, true),
+            };
+            if text.ends_with(MISSING_NEWLINE) {
+                text.pop();
+                hunk.old_missing_newline |= old_side;
+                hunk.new_missing_newline |= new_side;
+            }
+        }
+    }
+    FileDiff {
+        old_file: old_path.to_string(),
+        new_file: new_path.to_string(),
+        hunks,
+        is_rename: old_path != new_path && old_path != "/dev/null" && new_path != "/dev/null",
+        ..Default::default()
+    }
+}
+
+/// The unified diff turning `old` (the content of `old_path`) into `new`,
+/// or an empty string when they are the same.
+pub fn unified_diff(
+    old_path: &str,
+    new_path: &str,
+    old: &str,
+    new: &str,
+    context: usize,
+) -> String {
+    let file_diff = diff_contents(old_path, new_path, old, new, context);
+    if file_diff.hunks.is_empty() && old_path == new_path {
+        return String::new();
+    }
+    format_hunks(old_path, new_path, &file_diff.hunks)
+}
+
+/// Like [`unified_diff`], in the `git diff` format written by the
+/// [`FileDiff`] `Display` implementation, with the creation, deletion or
+/// rename lines `git apply` expects.
+pub fn git_diff(old_path: &str, new_path: &str, old: &str, new: &str, context: usize) -> String {
+    let file_diff = diff_contents(old_path, new_path, old, new, context);
+    if file_diff.hunks.is_empty() && old_path == new_path {
+        return String::new();
+    }
+    file_diff.to_string()
+}
+
+/// Reads a file for diffing, or `None` when it is binary. A missing side
+/// reads as empty.
+fn read_text(path: Option<&Path>) -> io::Result<Option<String>> {
+    let Some(path) = path else {
+        return Ok(Some(String::new()));
+    };
+    let bytes = fs::read(path)?;
+    if bytes.contains(&0) {
+        return Ok(None);
+    }
+    Ok(String::from_utf8(bytes).ok())
+}
+
+/// Adds the diff between two files to `patch`, or their name to
+/// `binary_files` when one of them is binary and they differ.
+fn add_file_diff(
+    (old_name, old): (&str, Option<&Path>),
+    (new_name, new): (&str, Option<&Path>),
+    context: usize,
+    patch: &mut Patch,
+    binary_files: &mut Vec<String>,
+) -> io::Result<()> {
+    match (read_text(old)?, read_text(new)?) {
+        (Some(old_content), Some(new_content)) => {
+            let file_diff = diff_contents(old_name, new_name, &old_content, &new_content, context);
+            if !file_diff.hunks.is_empty() || old_name != new_name {
+                patch.diffs.push(file_diff);
+            }
+        }
+        _ => {
+            let read = |path: Option<&Path>| path.map(fs::read).transpose();
+            if read(old)? != read(new)? {
+                let name = if old.is_some() { old_name } else { new_name };
+                binary_files.push(name.to_string());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Diffs two files, or two directories file by file, into a patch.
+///
+/// Two files give a single section named after `old`, so the patch applies
+/// to it; `/dev/null` on either side makes it a creation or deletion. For
+/// directories, the sections are named by the path relative to each root,
+/// and files present on one side only are created or deleted. Hidden files
+/// and files excluded by `.gitignore` are skipped, as are binary files: the
+/// paths of those that differ are returned alongside the patch.
+pub fn diff_paths(old: &Path, new: &Path, context: usize) -> io::Result<(Patch, Vec<String>)> {
+    let mut patch = Patch::default();
+    let mut binary_files = Vec::new();
+
+    if !old.is_dir() && !new.is_dir() {
+        let dev_null = Path::new("/dev/null");
+        let old_name = old.to_string_lossy();
+        let new_name = if old == dev_null || new == dev_null {
+            new.to_string_lossy()
+        } else {
+            old_name.clone()
+        };
+        add_file_diff(
+            (&old_name, (old != dev_null).then_some(old)),
+            (&new_name, (new != dev_null).then_some(new)),
+            context,
+            &mut patch,
+            &mut binary_files,
+        )?;
+        return Ok((patch, binary_files));
+    }
+    if !old.is_dir() || !new.is_dir() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "cannot compare a directory with a file",
+        ));
+    }
+
+    let old_files: BTreeSet<PathBuf> = discovery::list_files(old).into_iter().collect();
+    let new_files: BTreeSet<PathBuf> = discovery::list_files(new).into_iter().collect();
+    let all: BTreeSet<&PathBuf> = old_files.union(&new_files).collect();
+    for relative in all {
+        let name = relative.to_string_lossy();
+        let old_path = old_files.contains(relative).then(|| old.join(relative));
+        let new_path = new_files.contains(relative).then(|| new.join(relative));
+        add_file_diff(
+            (
+                if old_path.is_some() {
+                    &name
+                } else {
+                    "/dev/null"
+                },
+                old_path.as_deref(),
+            ),
+            (
+                if new_path.is_some() {
+                    &name
+                } else {
+                    "/dev/null"
+                },
+                new_path.as_deref(),
+            ),
+            context,
+            &mut patch,
+            &mut binary_files,
+        )?;
+    }
+    Ok((patch, binary_files))
+}
+
+/// Adds terminal colors to a unified diff: bold headers, cyan `@@` lines,
+/// green additions and red removals.
+pub fn colorize(diff: &str) -> String {
+    let lines: Vec<&str> = diff.lines().collect();
+    let mut out = String::with_capacity(diff.len());
+    for (idx, line) in lines.iter().enumerate() {
+        let is_header = line.starts_with("diff ")
+            || (line.starts_with("--- ")
+                && lines
+                    .get(idx + 1)
+                    .is_some_and(|next| next.starts_with("+++ ")))
+            || (line.starts_with("+++ ") && idx > 0 && lines[idx - 1].starts_with("--- "));
+        let color = if is_header {
+            BOLD
+        } else if line.starts_with("@@") {
+            CYAN
+        } else if line.starts_with('+') {
+            GREEN
+        } else if line.starts_with('-') {
+            RED
+        } else {
+            ""
+        };
+        if color.is_empty() {
+            out.push_str(line);
+        } else {
+            out.push_str(color);
+            out.push_str(line);
+            out.push_str(RESET);
+        }
+        out.push('\n');
+    }
+    out
+}
@@ -0,0 +1,288 @@
+use crate::diff::{FileDiff, Hunk, Line};
+use crate::elision::ElisionMatcher;
+use crate::patcher;
+use std::path::Path;
+
+/// A fenced code block holding the complete new content of a file.
+#[derive(Debug)]
+pub struct FileBlock {
+    pub path: String,
+    pub lines: Vec<String>,
+}
+
+/// A block is only treated as shorter-than-expected above this many lines.
+const MIN_LINES_FOR_LENGTH_CHECK: usize = 20;
+const MIN_LENGTH_RATIO: f32 = 0.5;
+
+/// Extensions an unlabeled header comment must end with to name a file.
+const KNOWN_EXTENSIONS: &[&str] = &[
+    "c", "cc", "cpp", "cs", "css", "dart", "ex", "exs", "go", "h", "hpp", "hs", "html", "java",
+    "js", "json", "jsx", "kt", "lua", "md", "ml", "php", "pl", "py", "r", "rb", "rs", "scala",
+    "scss", "sh", "sql", "svelte", "swift", "toml", "ts", "tsx", "txt", "vue", "xml", "yaml",
+    "yml",
+];
+
+/// Languages where `#` starts a comment (and `//` does not).
+const HASH_COMMENT_EXTENSIONS: &[&str] = &[
+    "bash", "cmake", "ex", "exs", "jl", "nim", "pl", "ps1", "py", "r", "rb", "sh", "toml", "yaml",
+    "yml", "zsh",
+];
+
+/// Languages where `'` also marks lifetimes or type variables, so only
+/// character literals are skipped.
+const CHAR_LITERAL_EXTENSIONS: &[&str] = &["hs", "ml", "mli", "rs"];
+
+fn extension(path: &str) -> Option<String> {
+    Path::new(path)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(str::to_lowercase)
+}
+
+pub(crate) fn looks_like_path(token: &str) -> bool {
+    let token = token.trim_matches(|c| c == '"' || c == '\'' || c == '`');
+    let has_extension = extension(token).is_some_and(|ext| {
+        ext.chars().all(|c| c.is_ascii_alphanumeric())
+            && ext.chars().any(|c| c.is_ascii_alphabetic())
+    });
+    !token.is_empty()
+        && !token.starts_with('.')
+        && (token.contains('/') || has_extension)
+        && !token.contains(char::is_whitespace)
+}
+
+/// Whether an unlabeled comment is a path: it needs a directory and a
+/// known extension, so `# e.g. this` or `// v1.0` are left alone.
+fn looks_like_bare_path(token: &str) -> bool {
+    looks_like_path(token)
+        && token.contains('/')
+        && extension(token).is_some_and(|ext| KNOWN_EXTENSIONS.contains(&ext.as_str()))
+}
+
+/// Reads a path from a fence info string: "rust src/lib.rs", "src/lib.rs",
+/// "rust:src/lib.rs" or `rust title="src/lib.rs"`.
+fn path_from_fence_info(info: &str) -> Option<String> {
+    for token in info.split_whitespace() {
+        let candidate = token
+            .strip_prefix("title=")
+            .or_else(|| token.split_once(':').map(|(_, path)| path))
+            .unwrap_or(token)
+            .trim_matches(|c| c == '"' || c == '\'');
+        if looks_like_path(candidate) {
+            return Some(candidate.to_string());
+        }
+    }
+    None
+}
+
+/// Reads a path from a first line like `// File: src/lib.rs`,
+/// `# filepath: app.py` or `// src/lib.rs`. Without a label, the comment
+/// must hold a path with a directory and a known extension.
+fn path_from_header_comment(line: &str) -> Option<String> {
+    let trimmed = line.trim();
+    let body = ["//", "#", "--", "/*", "<!--", ";"]
+        .iter()
+        .find_map(|prefix| trimmed.strip_prefix(prefix))?
+        .trim()
+        .trim_end_matches("*/")
+        .trim_end_matches("-->")
+        .trim();
+    let lowered = body.to_lowercase();
+    let labeled = ["file:", "filepath:", "filename:", "path:"]
+        .iter()
+        .find_map(|label| {
+            lowered
+                .starts_with(label)
+                .then(|| body[label.len()..].trim())
+        });
+    match labeled {
+        Some(path) => looks_like_path(path).then(|| path.to_string()),
+        None => looks_like_bare_path(body).then(|| body.to_string()),
+    }
+}
+
+/// Extracts every fenced code block that names the file it contains.
+pub fn extract_file_blocks(input: &str) -> Vec<FileBlock> {
+    let lines: Vec<&str> = input.lines().collect();
+    let mut blocks = Vec::new();
+    let mut idx = 0;
+
+    while idx < lines.len() {
+        let Some(info) = lines[idx].trim_start().strip_prefix("```") else {
+            idx += 1;
+            continue;
+        };
+        let start = idx + 1;
+        let end = lines[start..]
+            .iter()
+            .position(|l| l.trim() == "```")
+            .map(|pos| start + pos)
+            .unwrap_or(lines.len());
+        let mut content: Vec<String> = lines[start..end].iter().map(|l| l.to_string()).collect();
+
+        let path = path_from_fence_info(info).or_else(|| {
+            let path = path_from_header_comment(content.first()?)?;
+            content.remove(0);
+            Some(path)
+        });
+        if let Some(path) = path {
+            blocks.push(FileBlock {
+                path,
+                lines: content,
+            });
+        }
+        idx = end + 1;
+    }
+    blocks
+}
+
+pub(crate) fn is_whole_file_input(input: &str) -> bool {
+    !extract_file_blocks(input).is_empty()
+}
+
+/// Builds a [`FileDiff`] replacing `old_lines` with the content of `block`.
+/// `old_lines` is `None` when the file does not exist yet.
+pub fn build_whole_file_diff(block: &FileBlock, old_lines: Option<&[String]>) -> FileDiff {
+    let Some(old_lines) = old_lines else {
+        return FileDiff {
+            old_file: "/dev/null".to_string(),
+            new_file: block.path.clone(),
+            hunks: vec![Hunk {
+                new_start: 1,
+                new_lines: block.lines.len(),
+                lines: block.lines.iter().cloned().map(Line::Addition).collect(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+    };
+
+    let mut lines = block.lines.clone();
+    // A header comment naming the file is kept when the file really has it.
+    if let Some(first) = old_lines.first()
+        && path_from_header_comment(first).as_deref() == Some(block.path.as_str())
+        && block.lines.first() != Some(first)
+    {
+        lines.insert(0, first.clone());
+    }
+
+    let diff_lines = patcher::diff_lines_by(old_lines, &lines, |l| l.to_string());
+    let mut file_diff = FileDiff {
+        old_file: block.path.clone(),
+        new_file: block.path.clone(),
+        ..Default::default()
+    };
+    if diff_lines.iter().any(|l| !matches!(l, Line::Context(_))) {
+        file_diff.hunks.push(Hunk {
+            old_start: 1,
+            old_lines: old_lines.len(),
+            new_start: 1,
+            new_lines: lines.len(),
+            lines: diff_lines,
+            ..Default::default()
+        });
+    }
+    file_diff
+}
+
+/// Net count of unclosed brackets, ignoring the content of simple string
+/// literals and line comments in the syntax of the language of `path`.
+fn bracket_balance(path: &str, lines: &[String]) -> i64 {
+    let ext = extension(path).unwrap_or_default();
+    let hash_comments = HASH_COMMENT_EXTENSIONS.contains(&ext.as_str());
+    let char_literals_only = CHAR_LITERAL_EXTENSIONS.contains(&ext.as_str());
+
+    let mut balance = 0;
+    for line in lines {
+        let chars: Vec<char> = line.chars().collect();
+        let mut quote: Option<char> = None;
+        let mut escaped = false;
+        let mut idx = 0;
+        while idx < chars.len() {
+            let c = chars[idx];
+            idx += 1;
+            if let Some(q) = quote {
+                if escaped {
+                    escaped = false;
+                } else if c == '\\' {
+                    escaped = true;
+                } else if c == q {
+                    quote = None;
+                }
+                continue;
+            }
+            match c {
+                '"' | '`' => quote = Some(c),
+                '\'' if !char_literals_only => quote = Some(c),
+                // `'{'` is a character literal, `'a` a lifetime.
+                '\'' => {
+                    if chars.get(idx) == Some(&'\\') {
+                        quote = Some(c);
+                    } else if chars.get(idx + 1) == Some(&'\'') {
+                        idx += 2;
+                    }
+                }
+                '#' if hash_comments => break,
+                '/' if !hash_comments && chars.get(idx) == Some(&'/') => break,
+                '{' | '(' | '[' => balance += 1,
+                '}' | ')' | ']' => balance -= 1,
+                _ => {}
+            }
+        }
+    }
+    balance
+}
+
+/// Looks for signs that an LLM cut the file short: elision markers, brackets
+/// left open where the original was balanced, a dangling last line, or a
+/// block much shorter than the current file.
+pub fn truncation_warnings(
+    path: &str,
+    old_lines: Option<&[String]>,
+    new_lines: &[String],
+    elision: &ElisionMatcher,
+) -> Vec<String> {
+    let mut warnings = Vec::new();
+
+    if let Some(marker) = new_lines.iter().find(|l| elision.is_marker(l)) {
+        warnings.push(format!(
+            "The block contains an elision marker ('{}'); it is probably a partial file (see --lazy).",
+            marker.trim()
+        ));
+    }
+
+    let new_balance = bracket_balance(path, new_lines);
+    let old_balance = old_lines.map_or(0, |lines| bracket_balance(path, lines));
+    if new_balance > old_balance {
+        warnings.push(format!(
+            "The block leaves {} bracket(s) open; it may end in the middle of a function.",
+            new_balance - old_balance
+        ));
+    }
+
+    if let Some(last) = new_lines.iter().rev().find(|l| !l.trim().is_empty()) {
+        let last = last.trim_end();
+        if last.ends_with(',')
+            || last.ends_with('\\')
+            || (last.ends_with(':') && last.starts_with(char::is_whitespace))
+        {
+            warnings.push(format!(
+                "The block ends with an incomplete line: '{}'",
+                last.trim()
+            ));
+        }
+    }
+
+    if let Some(old_lines) = old_lines
+        && old_lines.len() >= MIN_LINES_FOR_LENGTH_CHECK
+        && (new_lines.len() as f32) < old_lines.len() as f32 * MIN_LENGTH_RATIO
+    {
+        warnings.push(format!(
+            "The block has {} lines but the current file has {}.",
+            new_lines.len(),
+            old_lines.len()
+        ));
+    }
+
+    warnings
+}
@@ -82,3 +82,84 @@ fn test_parses_diff_with_no_headers() {
     assert!(file_diff.new_file.is_empty());
     assert_eq!(file_diff.hunks.len(), 1);
 }
+
+#[test]
+fn test_parses_paths_containing_spaces() {
+    let diff_content = "--- a/My Docs/notes.md\n+++ b/My Docs/notes.md\n@@ -1 +1 @@\n-old\n+new\n";
+    let patch = parser::parse_patch(diff_content).unwrap();
+    assert_eq!(patch.diffs[0].old_file, "My Docs/notes.md");
+    assert_eq!(patch.diffs[0].new_file, "My Docs/notes.md");
+}
+
+#[test]
+fn test_parses_git_quoted_paths() {
+    // ARRANGE: git quotes non-ASCII paths and escapes them as octal bytes.
+    let diff_content = r#"diff --git "a/caf\303\251 menu.txt" "b/caf\303\251 menu.txt"
+--- "a/caf\303\251 menu.txt"
++++ "b/caf\303\251 menu.txt"
+@@ -1 +1 @@
+-old
++new
+"#;
+    // ACT
+    let patch = parser::parse_patch(diff_content).unwrap();
+
+    // ASSERT
+    assert_eq!(patch.diffs[0].old_file, "café menu.txt");
+    assert_eq!(patch.diffs[0].new_file, "café menu.txt");
+}
+
+#[test]
+fn test_strips_diff_u_timestamps() {
+    let diff_content = "--- old dir/file.txt\t2024-05-01 10:00:00.000000000 +0200\n+++ new dir/file.txt\t2024-05-02 11:30:00.000000000 +0200\n@@ -1 +1 @@\n-old\n+new\n";
+    let patch = parser::parse_patch(diff_content).unwrap();
+    assert_eq!(patch.diffs[0].old_file, "old dir/file.txt");
+    assert_eq!(patch.diffs[0].new_file, "new dir/file.txt");
+}
+
+#[test]
+fn test_falls_back_to_diff_git_line_for_paths() {
+    // ARRANGE: The ---/+++ headers were dropped.
+    let diff_content = "diff --git a/src/my file.rs b/src/my file.rs\n@@ -1 +1 @@\n-old\n+new\n";
+
+    // ACT
+    let patch = parser::parse_patch(diff_content).unwrap();
+
+    // ASSERT
+    assert_eq!(patch.diffs.len(), 1);
+    assert_eq!(patch.diffs[0].old_file, "src/my file.rs");
+    assert_eq!(patch.diffs[0].new_file, "src/my file.rs");
+}
+
+#[test]
+fn test_splits_sections_without_diff_git_lines() {
+    // ARRANGE: The first hunk removes a SQL comment starting with `--`.
+    let diff_content = "--- a/schema.sql\n+++ b/schema.sql\n@@ -1,2 +1,1 @@\n--- old comment\n create table t;\n--- a/src/b/mod.rs\n+++ b/src/b/mod.rs\n@@ -1 +1 @@\n-x\n+b\n";
+
+    // ACT
+    let patch = parser::parse_patch(diff_content).unwrap();
+
+    // ASSERT
+    assert_eq!(patch.diffs.len(), 2);
+    assert_eq!(patch.diffs[0].new_file, "schema.sql");
+    assert_eq!(patch.diffs[0].hunks[0].lines.len(), 2);
+    assert_eq!(patch.diffs[1].new_file, "src/b/mod.rs");
+}
+
+#[test]
+fn test_reads_git_metadata_of_every_file() {
+    // ARRANGE: The metadata of the second file directly follows a hunk.
+    let diff_content = "diff --git a/gone.txt b/gone.txt\ndeleted file mode 100644\n--- a/gone.txt\n+++ /dev/null\n@@ -1 +0,0 @@\n-old\ndiff --git a/x.rs b/y.rs\nsimilarity index 90%\nrename from x.rs\nrename to y.rs\n--- a/x.rs\n+++ b/y.rs\n@@ -1 +1 @@\n-a\n+b\n";
+
+    // ACT
+    let patch = parser::parse_patch(diff_content).unwrap();
+
+    // ASSERT
+    assert_eq!(patch.diffs.len(), 2);
+    assert!(!patch.diffs[0].is_rename);
+    assert!(patch.diffs[1].is_rename);
+    assert_eq!(patch.diffs[1].old_file, "x.rs");
+    assert_eq!(patch.diffs[1].new_file, "y.rs");
+    assert_eq!(patch.diffs[1].hunks.len(), 1);
+    assert_eq!(patch.diffs[1].hunks[0].lines.len(), 2);
+}
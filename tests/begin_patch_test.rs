@@ -0,0 +1,99 @@
+use mend::begin_patch::parse_begin_patch;
+use mend::diff::Line;
+use mend::parser::{DiffFormat, detect_format};
+use mend::patcher;
+
+fn to_lines(s: &str) -> Vec<String> {
+    s.lines().map(String::from).collect()
+}
+
+const AGENT_PATCH: &str = r#"Sure, here is the change:
+
+*** Begin Patch
+*** Add File: docs/notes.md
++# Notes
++First entry
+*** Delete File: old/legacy.py
+*** Update File: src/app.py
+*** Move to: src/application.py
+@@ class Greeter
+@@     def greet(self):
+-        return "hi"
++        return "hello"
+*** End Patch
+"#;
+
+#[test]
+fn test_detects_begin_patch_format() {
+    assert_eq!(detect_format(AGENT_PATCH), DiffFormat::BeginPatch);
+}
+
+#[test]
+fn test_maps_sections_to_file_diffs() {
+    // ACT
+    let patch = parse_begin_patch(AGENT_PATCH).unwrap();
+
+    // ASSERT
+    assert_eq!(patch.diffs.len(), 3);
+
+    let added = &patch.diffs[0];
+    assert_eq!(added.old_file, "/dev/null");
+    assert_eq!(added.new_file, "docs/notes.md");
+    assert_eq!(added.hunks[0].lines.len(), 2);
+
+    let deleted = &patch.diffs[1];
+    assert_eq!(deleted.old_file, "old/legacy.py");
+    assert_eq!(deleted.new_file, "/dev/null");
+
+    let updated = &patch.diffs[2];
+    assert_eq!(updated.old_file, "src/app.py");
+    assert_eq!(updated.new_file, "src/application.py");
+    assert!(updated.is_rename);
+    assert_eq!(updated.hunks.len(), 1);
+    assert_eq!(
+        updated.hunks[0].section_hints,
+        vec!["class Greeter".to_string(), "def greet(self):".to_string()]
+    );
+    assert!(matches!(&updated.hunks[0].lines[0], Line::Removal(_)));
+}
+
+#[test]
+fn test_rejects_unknown_directives() {
+    let patch = "*** Begin Patch\n*** Frobnicate File: x\n*** End Patch\n";
+    assert!(parse_begin_patch(patch).is_err());
+}
+
+#[test]
+fn test_section_hints_pick_the_right_duplicate() {
+    // ARRANGE: The same method body exists in two classes.
+    let source = to_lines(
+        "class Cat:\n    def speak(self):\n        return \"hi\"\n\nclass Greeter:\n    def greet(self):\n        return \"hi\"",
+    );
+    let patch = parse_begin_patch(
+        "*** Begin Patch\n*** Update File: a.py\n@@ class Greeter\n-        return \"hi\"\n+        return \"hello\"\n*** End Patch",
+    )
+    .unwrap();
+    let hunk = &patch.diffs[0].hunks[0];
+
+    // ACT
+    let section_start = patcher::find_section_start(&source, &hunk.section_hints).unwrap();
+    let (clean_source_map, clean_index_map) = patcher::build_lookup_tables(&source);
+    let matches = patcher::find_fuzzy_match(
+        &source,
+        &clean_source_map,
+        &clean_index_map,
+        hunk,
+        patcher::MatchOptions {
+            fuzziness: 1,
+            min_line: 0,
+            debug_mode: false,
+            match_threshold: 0.7,
+        },
+    );
+    let narrowed = patcher::narrow_by_section(matches, section_start);
+
+    // ASSERT
+    assert_eq!(section_start, 4);
+    assert_eq!(narrowed.len(), 1);
+    assert_eq!(narrowed[0].start_index, 6);
+}
@@ -0,0 +1,119 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+
+/// A scratch directory holding `files`, removed and recreated on each call.
+fn work_dir(name: &str, files: &[(&str, &str)]) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("mend-cli-{name}-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    for (path, content) in files {
+        let path = dir.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+    dir
+}
+
+fn mend(dir: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_mend"))
+        .args(args)
+        .current_dir(dir)
+        .stdin(Stdio::null())
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn test_normalize_accepts_documented_options() {
+    // ARRANGE: The second hunk matches nowhere.
+    let dir = work_dir(
+        "normalize",
+        &[
+            ("f.txt", "a\nb\nc\n"),
+            (
+                "llm_answer.md",
+                "--- a/f.txt\n+++ b/f.txt\n@@ -1,3 +1,3 @@\n a\n-b\n+B\n c\n\
+                 @@ -9,2 +9,2 @@\n zz\n-yy\n+YY\n",
+            ),
+        ],
+    );
+
+    // ACT: The invocation shown in the README.
+    let output = mend(
+        &dir,
+        &[
+            "normalize",
+            "--on-failed",
+            "skip",
+            "-o",
+            "fixed.diff",
+            "llm_answer.md",
+        ],
+    );
+
+    // ASSERT
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(5), "{stderr}");
+    let fixed = fs::read_to_string(dir.join("fixed.diff")).unwrap();
+    assert!(fixed.contains("@@ -1,3 +1,3 @@\n a\n-b\n+B\n c\n"));
+    assert!(!fixed.contains("YY"));
+    assert_eq!(fs::read_to_string(dir.join("f.txt")).unwrap(), "a\nb\nc\n");
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_normalize_names_files_relative_to_root() {
+    // ARRANGE
+    let dir = work_dir(
+        "normalize-root",
+        &[
+            ("proj/src/f.txt", "a\nb\nc\n"),
+            (
+                "in.diff",
+                "--- a/src/f.txt\n+++ b/src/f.txt\n@@ -1,3 +1,3 @@\n a\n-b\n+B\n c\n",
+            ),
+        ],
+    );
+
+    // ACT
+    let output = mend(&dir, &["normalize", "--root", "proj", "in.diff"]);
+
+    // ASSERT: The result applies from inside the root, as the input did.
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(0), "{stderr}");
+    let normalized = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        normalized
+            .starts_with("diff --git a/src/f.txt b/src/f.txt\n--- a/src/f.txt\n+++ b/src/f.txt\n")
+    );
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_whole_file_path_is_checked_before_reading() {
+    // ARRANGE: A block naming a file outside the working directory.
+    let dir = work_dir(
+        "whole-file-escape",
+        &[
+            ("secret.txt", "secret\n"),
+            (
+                "proj/answer.md",
+                "```text sub/../../secret.txt\npublic\n```\n",
+            ),
+        ],
+    );
+
+    // ACT: Interactive, so the file would be shown before a prompt.
+    let output = mend(&dir.join("proj"), &["answer.md"]);
+
+    // ASSERT
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(2), "{stderr}");
+    assert!(stderr.contains("Refusing to touch"));
+    assert!(!stderr.contains("whole content"));
+    assert_eq!(
+        fs::read_to_string(dir.join("secret.txt")).unwrap(),
+        "secret\n"
+    );
+    fs::remove_dir_all(&dir).unwrap();
+}
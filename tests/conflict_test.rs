@@ -0,0 +1,75 @@
+use clap::ValueEnum;
+use mend::conflict::{self, ConflictStyle};
+use mend::diff::{Hunk, Line};
+use mend::patcher::MatchOptions;
+
+fn to_lines(s: &str) -> Vec<String> {
+    s.lines().map(String::from).collect()
+}
+
+fn stale_hunk() -> Hunk {
+    Hunk {
+        old_start: 2,
+        lines: vec![
+            Line::Context("fn main() {".to_string()),
+            Line::Removal("    let x = 10;".to_string()),
+            Line::Addition("    let x = 11;".to_string()),
+            Line::Context("}".to_string()),
+        ],
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_parses_conflict_styles() {
+    let parse = |s| ConflictStyle::from_str(s, false);
+    assert_eq!(parse("merge"), Ok(ConflictStyle::Merge));
+    assert_eq!(parse("diff3"), Ok(ConflictStyle::Diff3));
+    assert!(parse("zdiff3").is_err());
+}
+
+#[test]
+fn test_finds_best_location_below_threshold() {
+    // ARRANGE
+    let source = to_lines("// header\nfn main() {\n    let a = 1;\n}\n// footer");
+
+    // ACT
+    let location = conflict::find_best_location(
+        &source,
+        &stale_hunk(),
+        MatchOptions {
+            fuzziness: 2,
+            min_line: 0,
+            debug_mode: false,
+            match_threshold: 0.7,
+        },
+    )
+    .expect("a location");
+
+    // ASSERT
+    assert_eq!(location.start_index, 1);
+    assert_eq!(location.matched_length, 3);
+}
+
+#[test]
+fn test_inserts_diff3_conflict_block() {
+    // ARRANGE
+    let mut source = to_lines("// header\nfn main() {\n    let a = 1;\n}");
+
+    // ACT
+    let block_len = conflict::insert_conflict(
+        &mut source,
+        &stale_hunk(),
+        1,
+        3,
+        ConflictStyle::Diff3,
+        "diff (hunk 1)",
+    );
+
+    // ASSERT
+    let expected = to_lines(
+        "// header\n<<<<<<< current\nfn main() {\n    let a = 1;\n}\n||||||| expected by diff (hunk 1)\nfn main() {\n    let x = 10;\n}\n=======\nfn main() {\n    let x = 11;\n}\n>>>>>>> diff (hunk 1)",
+    );
+    assert_eq!(source, expected);
+    assert_eq!(block_len, expected.len() - 1);
+}
@@ -0,0 +1,142 @@
+use mend::decisions::{self, Choice, Decision, DecisionLog, Replay};
+use mend::parser::parse_patch;
+use mend::patcher::{HunkMatch, MatchStrategy};
+use std::fs;
+
+fn to_lines(s: &str) -> Vec<String> {
+    s.lines().map(String::from).collect()
+}
+
+fn decision(source: &str, choice: Choice) -> Decision {
+    Decision {
+        file: "src/lib.rs".to_string(),
+        hunk: "1234".to_string(),
+        source: source.to_string(),
+        choice,
+    }
+}
+
+#[test]
+fn test_hunk_hash_ignores_line_numbers() {
+    // ARRANGE
+    let first = parse_patch("@@ -3,2 +3,2 @@\n a\n-b\n+B\n").unwrap();
+    let moved = parse_patch("@@ -40,2 +41,2 @@\n a\n-b\n+B\n").unwrap();
+    let other = parse_patch("@@ -3,2 +3,2 @@\n a\n-b\n+C\n").unwrap();
+
+    // ACT
+    let hash = |patch: &mend::diff::Patch| decisions::hunk_hash(&patch.diffs[0].hunks[0]);
+
+    // ASSERT
+    assert_eq!(hash(&first), hash(&moved));
+    assert_ne!(hash(&first), hash(&other));
+    assert_eq!(hash(&first).len(), 16);
+}
+
+#[test]
+fn test_content_hash_is_stable() {
+    // The value is persisted, so it must not change between builds.
+    assert_eq!(decisions::content_hash(&[]), "cbf29ce484222325");
+    assert_ne!(
+        decisions::content_hash(&to_lines("a\nb")),
+        decisions::content_hash(&to_lines("ab"))
+    );
+}
+
+#[test]
+fn test_find_prefers_decision_on_same_content() {
+    // ARRANGE: The same hunk was decided twice, on different content.
+    let log = DecisionLog {
+        decisions: vec![
+            decision("aaaa", Choice::Apply { line: 7 }),
+            decision("bbbb", Choice::Skip),
+        ],
+    };
+
+    // ACT
+    let same = log.find("src/lib.rs", "1234", "bbbb");
+    let changed = log.find("src/lib.rs", "1234", "cccc");
+    let unknown = log.find("src/main.rs", "1234", "aaaa");
+
+    // ASSERT
+    assert_eq!(same.unwrap().choice, Choice::Skip);
+    assert_eq!(changed.unwrap().source, "aaaa");
+    assert!(unknown.is_none());
+}
+
+#[test]
+fn test_round_trips_decision_file() {
+    // ARRANGE
+    let path = std::env::temp_dir().join(format!("mend-decisions-{}.json", std::process::id()));
+    let log = DecisionLog {
+        decisions: vec![
+            decision("aaaa", Choice::Apply { line: 7 }),
+            decision("bbbb", Choice::Skip),
+        ],
+    };
+
+    // ACT
+    log.save(&path).unwrap();
+    let text = fs::read_to_string(&path).unwrap();
+    let loaded = DecisionLog::load(&path).unwrap();
+
+    // ASSERT
+    assert!(text.contains("\"action\": \"apply\""));
+    assert!(text.contains("\"line\": 7"));
+    assert_eq!(loaded, log);
+    fs::remove_file(&path).unwrap();
+}
+
+fn candidate(start_index: usize) -> HunkMatch {
+    HunkMatch {
+        start_index,
+        matched_length: 2,
+        score: 0.8,
+        density: 1.0,
+        strategy: MatchStrategy::Fuzzy,
+    }
+}
+
+#[test]
+fn test_replay_applies_at_recorded_line() {
+    // ARRANGE: The hunk matches at lines 3 and 8; line 8 was chosen.
+    let matches = [candidate(2), candidate(7)];
+    let recorded = decision("aaaa", Choice::Apply { line: 8 });
+
+    // ACT
+    let replayed = decisions::replay(&recorded, "aaaa", &matches);
+
+    // ASSERT
+    match replayed {
+        Replay::Apply(chosen) => assert_eq!(chosen.start_index, 7),
+        other => panic!("expected the match at line 8, got {other:?}"),
+    }
+}
+
+#[test]
+fn test_replay_skips_hunk() {
+    // ARRANGE
+    let recorded = decision("aaaa", Choice::Skip);
+
+    // ACT
+    let replayed = decisions::replay(&recorded, "aaaa", &[candidate(2)]);
+
+    // ASSERT
+    assert!(matches!(replayed, Replay::Skip));
+}
+
+#[test]
+fn test_replay_fails_when_file_changed() {
+    // ARRANGE
+    let apply = decision("aaaa", Choice::Apply { line: 3 });
+    let skip = decision("aaaa", Choice::Skip);
+
+    // ACT
+    let changed = decisions::replay(&apply, "bbbb", &[candidate(2)]);
+    let skip_changed = decisions::replay(&skip, "bbbb", &[candidate(2)]);
+    let line_gone = decisions::replay(&apply, "aaaa", &[candidate(7)]);
+
+    // ASSERT
+    assert!(matches!(changed, Replay::Stale));
+    assert!(matches!(skip_changed, Replay::Stale));
+    assert!(matches!(line_gone, Replay::Stale));
+}
@@ -0,0 +1,101 @@
+use mend::diff::{Hunk, Line};
+use mend::discovery;
+use mend::patcher::MatchOptions;
+use std::fs;
+use std::path::PathBuf;
+
+const OPTIONS: MatchOptions = MatchOptions {
+    fuzziness: 2,
+    min_line: 0,
+    debug_mode: false,
+    match_threshold: 0.7,
+};
+
+fn to_lines(s: &str) -> Vec<String> {
+    s.lines().map(String::from).collect()
+}
+
+fn hunk() -> Hunk {
+    Hunk {
+        lines: vec![
+            Line::Context("pub fn a() {".to_string()),
+            Line::Removal("    1".to_string()),
+            Line::Addition("    2".to_string()),
+            Line::Context("}".to_string()),
+        ],
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_similar_paths_match_module_names() {
+    // ARRANGE
+    let files: Vec<PathBuf> = ["src/util/mod.rs", "src/main.rs", "crates/core/src/utils.rs"]
+        .iter()
+        .map(PathBuf::from)
+        .collect();
+
+    // ACT
+    let similar = discovery::similar_paths(&files, "src/utils.rs");
+
+    // ASSERT: The same file name with the longest shared suffix comes first.
+    assert_eq!(
+        similar,
+        vec![
+            PathBuf::from("crates/core/src/utils.rs"),
+            PathBuf::from("src/util/mod.rs")
+        ]
+    );
+}
+
+#[test]
+fn test_hunk_match_score() {
+    let exact = to_lines("pub fn a() {\n    1\n}");
+    let unrelated = to_lines("fn main() {}\n");
+    assert_eq!(discovery::hunk_match_score(&exact, &[hunk()], OPTIONS), 1.0);
+    assert_eq!(
+        discovery::hunk_match_score(&unrelated, &[hunk()], OPTIONS),
+        0.0
+    );
+}
+
+#[test]
+fn test_ranks_candidates_and_picks_clear_winner() {
+    // ARRANGE
+    let root = std::env::temp_dir().join(format!("mend-discovery-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    fs::create_dir_all(root.join("src/util")).unwrap();
+    fs::write(root.join("src/util/mod.rs"), "pub fn a() {\n    1\n}\n").unwrap();
+    fs::write(root.join("src/other.rs"), "fn b() {}\n").unwrap();
+
+    // ACT
+    let files = discovery::list_files(&root);
+    let candidates = discovery::rank_candidates(&root, &files, &[hunk()], OPTIONS);
+
+    // ASSERT
+    assert_eq!(files.len(), 2);
+    assert_eq!(candidates.len(), 1, "files no hunk matches are left out");
+    let winner = discovery::clear_winner(&candidates).expect("a clear winner");
+    assert_eq!(winner.path, PathBuf::from("src/util/mod.rs"));
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn test_discovers_target_of_headerless_diff() {
+    // ARRANGE: The diff file itself sits in the tree next to the candidates.
+    let root = std::env::temp_dir().join(format!("mend-headerless-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    fs::create_dir_all(root.join("src")).unwrap();
+    fs::write(root.join("src/a.rs"), "fn b() {}\n").unwrap();
+    fs::write(root.join("src/b.rs"), "// b\npub fn a() {\n    1\n}\n").unwrap();
+    fs::write(root.join("fix.diff"), " pub fn a() {\n-    1\n+    2\n }\n").unwrap();
+
+    // ACT
+    let files = discovery::list_files(&root);
+    let candidates = discovery::discover_targets(&root, &files, &[hunk()], OPTIONS);
+
+    // ASSERT
+    assert_eq!(candidates.len(), 1);
+    assert_eq!(candidates[0].path, PathBuf::from("src/b.rs"));
+    fs::remove_dir_all(&root).unwrap();
+}
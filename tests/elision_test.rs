@@ -0,0 +1,94 @@
+use mend::diff::Line;
+use mend::elision::ElisionMatcher;
+use mend::parser::parse_patch;
+use mend::patcher::{self, MatchOptions};
+
+fn to_lines(s: &str) -> Vec<String> {
+    s.lines().map(String::from).collect()
+}
+
+fn options() -> MatchOptions {
+    MatchOptions {
+        fuzziness: 2,
+        min_line: 0,
+        debug_mode: false,
+        match_threshold: 0.7,
+    }
+}
+
+const SOURCE: &str = "fn process(items: &[u32]) -> u32 {
+    let mut total = 0;
+    for item in items {
+        total += item;
+    }
+    log(total);
+    audit(total);
+    total
+}";
+
+#[test]
+fn test_elided_context_is_filled_from_the_file() {
+    // ARRANGE: The hunk skips the loop with a marker.
+    let source = to_lines(SOURCE);
+    let diff = "@@ -1,9 +1,9 @@
+ fn process(items: &[u32]) -> u32 {
+-    let mut total = 0;
++    let mut total: u32 = 0;
+     // ... unchanged ...
+     audit(total);
+-    total
++    total * 2
+ }";
+    let patch = parse_patch(diff).unwrap();
+    let hunk = &patch.diffs[0].hunks[0];
+    let elision = ElisionMatcher::default();
+
+    // ACT
+    assert!(patcher::find_strict_match(&source, hunk, 0, false).is_empty());
+    assert!(elision.has_markers(hunk));
+    let expanded = elision.expand_elisions(&source, hunk, options()).unwrap();
+    let matches = patcher::find_strict_match(&source, &expanded, 0, false);
+    let result = patcher::apply_hunk(
+        &source,
+        &expanded,
+        matches[0].start_index,
+        matches[0].matched_length,
+    );
+
+    // ASSERT
+    assert!(matches!(&expanded.lines[3], Line::Context(s) if s == "    for item in items {"));
+    assert_eq!(
+        result.join("\n"),
+        SOURCE
+            .replace("let mut total = 0;", "let mut total: u32 = 0;")
+            .replace("    total\n}", "    total * 2\n}")
+    );
+}
+
+#[test]
+fn test_missing_segment_cannot_be_expanded() {
+    let source = to_lines(SOURCE);
+    let diff = "@@ -1,3 +1,3 @@
+ fn process(items: &[u32]) -> u32 {
+ // ...
+-    does_not_exist();
++    still_missing();";
+    let patch = parse_patch(diff).unwrap();
+
+    let expanded =
+        ElisionMatcher::default().expand_elisions(&source, &patch.diffs[0].hunks[0], options());
+
+    assert!(expanded.is_none());
+}
+
+#[test]
+fn test_custom_and_disabled_patterns() {
+    let custom = ElisionMatcher::new(&[r"^\s*// SNIP$".to_string()]).unwrap();
+    assert!(custom.is_marker("    // SNIP"));
+    assert!(custom.is_marker("// ... existing code ..."));
+
+    let disabled = ElisionMatcher::disabled();
+    assert!(!disabled.is_marker("// ... existing code ..."));
+
+    assert!(ElisionMatcher::new(&["(".to_string()]).is_err());
+}
@@ -0,0 +1,83 @@
+use mend::error::{self, AppError};
+use mend::parser::ParseError;
+use mend::patcher::PatchError;
+use mend::report::{FileRecord, FileStatus, HunkRecord, HunkStatus, Report};
+use std::io;
+
+#[test]
+fn test_exit_codes_tell_failures_apart() {
+    // ARRANGE
+    let cases = [
+        (
+            AppError::Patch(PatchError::HunkApplicationFailed {
+                file_path: "f.txt".to_string(),
+                hunk_index: 0,
+                reason: "No matching context found in CI mode.".to_string(),
+            }),
+            error::EXIT_FAILED,
+        ),
+        (AppError::EmptyDiff, error::EXIT_INVALID_INPUT),
+        (
+            AppError::Parse(ParseError {
+                line_number: 3,
+                line_content: "@@ -x @@".to_string(),
+                message: "Malformed hunk header".to_string(),
+            }),
+            error::EXIT_INVALID_INPUT,
+        ),
+        (
+            AppError::InvalidDecisionFile {
+                path: "decisions.json".to_string(),
+                reason: "expected a sequence".to_string(),
+            },
+            error::EXIT_INVALID_INPUT,
+        ),
+        (
+            AppError::Patch(PatchError::AmbiguousMatch {
+                file_path: "f.txt".to_string(),
+                hunk_index: 0,
+            }),
+            error::EXIT_AMBIGUOUS,
+        ),
+        (
+            AppError::Io(io::Error::from(io::ErrorKind::NotFound)),
+            error::EXIT_IO,
+        ),
+        (
+            AppError::ConflictsLeft {
+                files: vec!["f.txt".to_string()],
+            },
+            error::EXIT_CONFLICTS,
+        ),
+    ];
+
+    // ACT & ASSERT
+    for (err, code) in &cases {
+        assert_eq!(err.exit_code(), *code, "{err}");
+    }
+}
+
+#[test]
+fn test_already_applied_run_is_partial() {
+    // ARRANGE: Every hunk of the diff was in the file already.
+    let report = Report {
+        files: vec![FileRecord {
+            path: "f.txt".to_string(),
+            old_path: None,
+            status: FileStatus::Modified,
+            reject_file: None,
+            hunks: vec![
+                HunkRecord::unplaced(0, HunkStatus::AlreadyApplied),
+                HunkRecord::unplaced(1, HunkStatus::AlreadyApplied),
+            ],
+        }],
+        ..Report::default()
+    };
+
+    // ACT
+    let code = report.exit_code(false);
+
+    // ASSERT
+    assert_eq!(code, error::EXIT_PARTIAL);
+    assert_ne!(code, error::EXIT_SUCCESS);
+}
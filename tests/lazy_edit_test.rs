@@ -0,0 +1,202 @@
+use mend::diff::Line;
+use mend::elision::ElisionMatcher;
+use mend::lazy_edit::build_lazy_edit_diff;
+use mend::patcher::{self, MatchOptions};
+
+fn to_lines(s: &str) -> Vec<String> {
+    s.lines().map(String::from).collect()
+}
+
+fn options() -> MatchOptions {
+    MatchOptions {
+        fuzziness: 2,
+        min_line: 0,
+        debug_mode: false,
+        match_threshold: 0.7,
+    }
+}
+
+fn apply_all(source: &[String], snippet: &str) -> String {
+    let file_diff = build_lazy_edit_diff(
+        snippet,
+        source,
+        "f.rs",
+        &ElisionMatcher::default(),
+        options(),
+    )
+    .expect("Building the lazy edit diff should succeed");
+    let mut lines = source.to_vec();
+    let mut min_line = 0;
+    for hunk in &file_diff.hunks {
+        let matches = patcher::find_strict_match(&lines, hunk, min_line, false);
+        assert_eq!(matches.len(), 1, "Generated hunks should match strictly");
+        lines = patcher::apply_hunk(
+            &lines,
+            hunk,
+            matches[0].start_index,
+            matches[0].matched_length,
+        );
+        min_line = matches[0].start_index;
+    }
+    lines.join("\n")
+}
+
+const SOURCE: &str = "use std::io;
+
+fn helper() -> u32 {
+    1
+}
+
+fn main() {
+    let x = helper();
+    println!(\"{}\", x);
+}
+
+fn other() {
+    // untouched
+}";
+
+#[test]
+fn test_elision_markers_are_recognized() {
+    let elision = ElisionMatcher::default();
+    assert!(elision.is_marker("// ... existing code ..."));
+    assert!(elision.is_marker("    # ... rest unchanged"));
+    assert!(elision.is_marker("# rest of the file unchanged"));
+    assert!(elision.is_marker("... existing code ..."));
+    assert!(elision.is_marker("<!-- ... -->"));
+    assert!(!elision.is_marker("    ...defaults,"));
+    assert!(!elision.is_marker("..."));
+    assert!(!elision.is_marker("    def stub(self): ..."));
+    assert!(!elision.is_marker("        ..."));
+    assert!(!elision.is_marker("// existing users are migrated below"));
+    assert!(!elision.is_marker("let x = 1;"));
+}
+
+#[test]
+fn test_lazy_snippet_edits_only_the_located_region() {
+    // ARRANGE
+    let source = to_lines(SOURCE);
+    let snippet = "```rust
+// ... existing code ...
+
+fn main() {
+    let x = helper();
+    let y = x * 2;
+    println!(\"{}\", y);
+}
+
+// ... existing code ...
+```";
+
+    // ACT
+    let result = apply_all(&source, snippet);
+
+    // ASSERT
+    assert_eq!(
+        result,
+        SOURCE.replace(
+            "    println!(\"{}\", x);",
+            "    let y = x * 2;\n    println!(\"{}\", y);"
+        )
+    );
+}
+
+#[test]
+fn test_lazy_snippet_with_several_segments() {
+    // ARRANGE
+    let source = to_lines(SOURCE);
+    let snippet = "fn helper() -> u32 {
+    2
+}
+// ... existing code ...
+fn other() {
+    // touched
+}";
+
+    // ACT
+    let file_diff = build_lazy_edit_diff(
+        snippet,
+        &source,
+        "f.rs",
+        &ElisionMatcher::default(),
+        options(),
+    )
+    .unwrap();
+    let result = apply_all(&source, snippet);
+
+    // ASSERT
+    assert_eq!(file_diff.hunks.len(), 2);
+    assert!(matches!(&file_diff.hunks[0].lines[1], Line::Removal(s) if s == "    1"));
+    assert_eq!(
+        result,
+        SOURCE
+            .replace("    1\n", "    2\n")
+            .replace("// untouched", "// touched")
+    );
+}
+
+#[test]
+fn test_lazy_snippet_keeps_indentation_changes() {
+    // ARRANGE: The only edit re-indents a line.
+    let source = to_lines(SOURCE);
+    let snippet = "// ... existing code ...
+fn main() {
+    let x = helper();
+        println!(\"{}\", x);
+}
+// ... existing code ...";
+
+    // ACT
+    let result = apply_all(&source, snippet);
+
+    // ASSERT
+    assert_eq!(
+        result,
+        SOURCE.replace("    println!(\"{}\", x);", "        println!(\"{}\", x);")
+    );
+}
+
+#[test]
+fn test_unknown_segment_is_an_error() {
+    let source = to_lines(SOURCE);
+    let snippet = "// ...\nfn brand_new() {}\n// ...";
+    let result = build_lazy_edit_diff(
+        snippet,
+        &source,
+        "f.rs",
+        &ElisionMatcher::default(),
+        options(),
+    );
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_generic_lines_do_not_place_new_code() {
+    // ARRANGE: `}` is in the file, but only as the end of other functions.
+    let source = to_lines("fn alpha() {\n    a();\n}\n\nfn beta() {\n    b();\n}");
+    let appended = "// ... existing code ...\n\nfn helper() {\n    do_something();\n}";
+    let enclosed = "// ...\nfn helper() {\n    do_something();\n}\n// ...";
+    let build = |snippet| {
+        build_lazy_edit_diff(
+            snippet,
+            &source,
+            "f.rs",
+            &ElisionMatcher::default(),
+            options(),
+        )
+    };
+
+    // ACT
+    let file_diff = build(appended).unwrap();
+    let ambiguous = build(enclosed);
+
+    // ASSERT: The new function goes to the end of the file.
+    assert_eq!(file_diff.hunks.len(), 1);
+    let hunk = &file_diff.hunks[0];
+    assert_eq!((hunk.old_start, hunk.old_lines), (source.len(), 0));
+    let added = &hunk.lines;
+    assert!(matches!(&added[0], Line::Addition(s) if s.is_empty()));
+    assert!(matches!(&added[1], Line::Addition(s) if s == "fn helper() {"));
+    assert!(added.iter().all(|l| matches!(l, Line::Addition(_))));
+    assert!(ambiguous.unwrap_err().message.contains("ambiguous"));
+}
@@ -0,0 +1,104 @@
+use clap::ValueEnum;
+use mend::parser::parse_patch;
+use mend::patcher::{self, AmbiguityPolicy, HunkMatch, MatchStrategy};
+
+fn to_lines(s: &str) -> Vec<String> {
+    s.lines().map(String::from).collect()
+}
+
+#[test]
+fn test_reports_strategy_of_each_match() {
+    // ARRANGE
+    let exact = to_lines("fn a() {\n    one();\n}");
+    let spaced = to_lines("fn a() {\n      one( );\n}");
+    let patch = parse_patch("@@ -1,3 +1,3 @@\n fn a() {\n-    one();\n+    two();\n }\n").unwrap();
+    let hunk = &patch.diffs[0].hunks[0];
+    let (clean_source_map, clean_index_map) = patcher::build_lookup_tables(&spaced);
+
+    // ACT
+    let strict = patcher::find_strict_match(&exact, hunk, 0, false);
+    let fuzzy = patcher::find_fuzzy_match(
+        &spaced,
+        &clean_source_map,
+        &clean_index_map,
+        hunk,
+        patcher::MatchOptions {
+            fuzziness: 2,
+            min_line: 0,
+            debug_mode: false,
+            match_threshold: 0.7,
+        },
+    );
+
+    // ASSERT
+    assert_eq!(strict[0].strategy, MatchStrategy::Exact);
+    assert_eq!(fuzzy.len(), 1);
+    assert_ne!(fuzzy[0].strategy, MatchStrategy::Exact);
+}
+
+#[test]
+fn test_finds_already_applied_hunk() {
+    // ARRANGE: The file already holds the new side of the hunk.
+    let source_lines = to_lines("a\nb\nc\nC\nd\ne");
+    let patch = parse_patch("@@ -2,3 +2,4 @@\n b\n c\n+C\n d\n").unwrap();
+    let hunk = &patch.diffs[0].hunks[0];
+
+    // ACT
+    let applied = patcher::find_applied_match(&source_lines, hunk);
+
+    // ASSERT
+    assert!(patcher::find_strict_match(&source_lines, hunk, 0, false).is_empty());
+    let applied = applied.unwrap();
+    assert_eq!(applied.start_index, 1);
+    assert_eq!(applied.matched_length, 4);
+}
+
+#[test]
+fn test_pending_hunk_is_not_already_applied() {
+    // ARRANGE
+    let source_lines = to_lines("a\nb\nc\nd");
+    let addition = parse_patch("@@ -2,2 +2,3 @@\n b\n+B\n c\n").unwrap();
+    let removal = parse_patch("@@ -2,3 +2,2 @@\n b\n-x\n c\n").unwrap();
+
+    // ACT
+    let pending = patcher::find_applied_match(&source_lines, &addition.diffs[0].hunks[0]);
+    let removed = patcher::find_applied_match(&source_lines, &removal.diffs[0].hunks[0]);
+
+    // ASSERT: Removals are never reported, as the context alone matches.
+    assert!(pending.is_none());
+    assert!(removed.is_none());
+}
+
+fn candidate(start_index: usize, score: f32) -> HunkMatch {
+    HunkMatch {
+        start_index,
+        matched_length: 2,
+        score,
+        density: 1.0,
+        strategy: MatchStrategy::Fuzzy,
+    }
+}
+
+#[test]
+fn test_ambiguity_policies_pick_one_match() {
+    // ARRANGE: The diff places the hunk at line index 40.
+    let matches = [candidate(10, 0.8), candidate(35, 0.75), candidate(90, 0.8)];
+    let pick = |policy| patcher::pick_match(&matches, policy, 40).map(|m| m.start_index);
+
+    // ACT & ASSERT: Ties on score go to the first match.
+    assert_eq!(pick(AmbiguityPolicy::Best), Some(10));
+    assert_eq!(pick(AmbiguityPolicy::Nearest), Some(35));
+    assert_eq!(pick(AmbiguityPolicy::First), Some(10));
+    assert_eq!(pick(AmbiguityPolicy::Fail), None);
+}
+
+#[test]
+fn test_parses_ambiguity_policy() {
+    assert_eq!(
+        AmbiguityPolicy::from_str("nearest", false),
+        Ok(AmbiguityPolicy::Nearest)
+    );
+    let best = AmbiguityPolicy::Best.to_possible_value().unwrap();
+    assert_eq!(best.get_name(), "best");
+    assert!(AmbiguityPolicy::from_str("closest", false).is_err());
+}
@@ -0,0 +1,178 @@
+use mend::diff::Line;
+use mend::myers;
+use mend::parser::parse_patch;
+use mend::unified;
+use std::fs;
+
+fn to_lines(s: &str) -> Vec<String> {
+    s.lines().map(String::from).collect()
+}
+
+/// Renders diff lines as `' '`, `'+'` and `'-'` prefixed text.
+fn render(lines: &[Line]) -> Vec<String> {
+    lines
+        .iter()
+        .map(|line| match line {
+            Line::Context(text) => format!(" {text}"),
+            Line::Addition(text) => format!("+{text}"),
+            Line::Removal(text) => format!("-{text}"),
+        })
+        .collect()
+}
+
+#[test]
+fn test_finds_shortest_edit() {
+    // ARRANGE: The classic example from Myers' paper.
+    let old = to_lines("a\nb\nc\na\nb\nb\na");
+    let new = to_lines("c\nb\na\nb\na\nc");
+
+    // ACT
+    let lines = myers::diff_lines(&old, &new);
+
+    // ASSERT
+    let edits = lines
+        .iter()
+        .filter(|l| !matches!(l, Line::Context(_)))
+        .count();
+    assert_eq!(edits, 5);
+    let rebuilt_old: Vec<&String> = lines
+        .iter()
+        .filter_map(|l| match l {
+            Line::Context(t) | Line::Removal(t) => Some(t),
+            Line::Addition(_) => None,
+        })
+        .collect();
+    let rebuilt_new: Vec<&String> = lines
+        .iter()
+        .filter_map(|l| match l {
+            Line::Context(t) | Line::Addition(t) => Some(t),
+            Line::Removal(_) => None,
+        })
+        .collect();
+    assert_eq!(rebuilt_old, old.iter().collect::<Vec<_>>());
+    assert_eq!(rebuilt_new, new.iter().collect::<Vec<_>>());
+}
+
+#[test]
+fn test_puts_removals_before_additions() {
+    // ARRANGE
+    let old = to_lines("keep\nold\nkeep too");
+    let new = to_lines("keep\nnew\nkeep too");
+
+    // ACT
+    let lines = myers::diff_lines(&old, &new);
+
+    // ASSERT
+    assert_eq!(render(&lines), [" keep", "-old", "+new", " keep too"]);
+}
+
+/// Deterministic pseudo-random lines drawn from a small alphabet, so the
+/// inputs share many lines in different orders.
+fn scrambled(seed: u64, len: usize) -> Vec<String> {
+    let mut state = seed;
+    (0..len)
+        .map(|_| {
+            state = state
+                .wrapping_mul(6_364_136_223_846_793_005)
+                .wrapping_add(1_442_695_040_888_963_407);
+            ((state >> 33) % 4).to_string()
+        })
+        .collect()
+}
+
+#[test]
+fn test_edit_script_is_shortest() {
+    for seed in 0..200 {
+        // ARRANGE
+        let old = scrambled(seed, (seed % 17) as usize);
+        let new = scrambled(seed + 1000, (seed % 13) as usize);
+
+        // ACT
+        let edits = myers::diff(&old, &new);
+
+        // ASSERT: As short as the one derived from the longest common
+        // subsequence, and it rebuilds both sides.
+        let lcs_len = lcs::LcsTable::new(&old, &new)
+            .longest_common_subsequence()
+            .len();
+        let changes = edits
+            .iter()
+            .filter(|e| !matches!(e, myers::Edit::Unchanged(..)))
+            .count();
+        assert_eq!(changes, old.len() + new.len() - 2 * lcs_len, "seed {seed}");
+        let lines = myers::diff_lines(&old, &new);
+        let side = |keep_removals: bool| -> Vec<String> {
+            lines
+                .iter()
+                .filter_map(|l| match l {
+                    Line::Context(t) => Some(t.clone()),
+                    Line::Removal(t) if keep_removals => Some(t.clone()),
+                    Line::Addition(t) if !keep_removals => Some(t.clone()),
+                    _ => None,
+                })
+                .collect()
+        };
+        assert_eq!(side(true), old, "seed {seed}");
+        assert_eq!(side(false), new, "seed {seed}");
+    }
+}
+
+#[test]
+fn test_diffs_unrelated_large_inputs() {
+    // ARRANGE: Nothing in common, the worst case for the edit distance.
+    let old: Vec<String> = (0..3000).map(|i| format!("old {i}")).collect();
+    let new: Vec<String> = (0..3000).map(|i| format!("new {i}")).collect();
+
+    // ACT
+    let lines = myers::diff_lines(&old, &new);
+
+    // ASSERT
+    assert_eq!(lines.len(), 6000);
+    assert!(matches!(&lines[0], Line::Removal(t) if t == "old 0"));
+    assert!(matches!(&lines[3000], Line::Addition(t) if t == "new 0"));
+}
+
+#[test]
+fn test_handles_empty_sides() {
+    assert!(myers::diff_lines(&[], &[]).is_empty());
+    assert_eq!(render(&myers::diff_lines(&[], &to_lines("a"))), ["+a"]);
+    assert_eq!(render(&myers::diff_lines(&to_lines("a"), &[])), ["-a"]);
+}
+
+#[test]
+fn test_diffs_directories() {
+    // ARRANGE
+    let root = std::env::temp_dir().join(format!("mend-diff-dirs-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    fs::create_dir_all(root.join("old/src")).unwrap();
+    fs::create_dir_all(root.join("new/src")).unwrap();
+    fs::write(root.join("old/src/lib.rs"), "a\nb\n").unwrap();
+    fs::write(root.join("new/src/lib.rs"), "a\nc\n").unwrap();
+    fs::write(root.join("old/same.txt"), "same\n").unwrap();
+    fs::write(root.join("new/same.txt"), "same\n").unwrap();
+    fs::write(root.join("old/gone.txt"), "bye\n").unwrap();
+    fs::write(root.join("new/added.txt"), "hi\n").unwrap();
+
+    // ACT
+    let (patch, binary_files) =
+        unified::diff_paths(&root.join("old"), &root.join("new"), 3).unwrap();
+
+    // ASSERT
+    assert!(binary_files.is_empty());
+    let sections: Vec<(&str, &str)> = patch
+        .diffs
+        .iter()
+        .map(|d| (d.old_file.as_str(), d.new_file.as_str()))
+        .collect();
+    assert_eq!(
+        sections,
+        [
+            ("/dev/null", "added.txt"),
+            ("gone.txt", "/dev/null"),
+            ("src/lib.rs", "src/lib.rs"),
+        ]
+    );
+    let reparsed = parse_patch(&patch.to_string()).unwrap();
+    assert_eq!(reparsed.diffs.len(), 3);
+    fs::remove_dir_all(&root).unwrap();
+}
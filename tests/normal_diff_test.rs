@@ -0,0 +1,118 @@
+use mend::diff::Line;
+use mend::normal::{parse_ed_script, parse_normal_diff};
+use mend::parser::{DiffFormat, detect_format};
+use mend::patcher;
+
+fn to_lines(s: &str) -> Vec<String> {
+    s.lines().map(String::from).collect()
+}
+
+#[test]
+fn test_detects_normal_and_ed_formats() {
+    assert_eq!(detect_format("2c2\n< b\n---\n> B\n"), DiffFormat::Normal);
+    assert_eq!(detect_format("4a\nE\n.\n2c\nB\n.\n"), DiffFormat::Ed);
+    assert_eq!(
+        detect_format("--- a/x\n+++ b/x\n@@ -1 +1 @@\n-a\n+b\n"),
+        DiffFormat::Unified
+    );
+}
+
+#[test]
+fn test_fenced_normal_diff_is_not_a_whole_file() {
+    // ARRANGE: The fence names a path, as whole-file blocks do.
+    let normal = "```diff src/a.txt\n2c2\n< b\n---\n> B\n```\n";
+    let ed = "```diff src/a.txt\n2c\nB\n.\n```\n";
+    let whole = "```rust src/a.rs\nfn a() {}\n```\n";
+
+    // ACT & ASSERT
+    assert_eq!(detect_format(normal), DiffFormat::Normal);
+    assert_eq!(detect_format(ed), DiffFormat::Ed);
+    assert_eq!(detect_format(whole), DiffFormat::WholeFile);
+    assert_eq!(parse_normal_diff(normal).unwrap().diffs[0].hunks.len(), 1);
+}
+
+#[test]
+fn test_parses_normal_diff_commands() {
+    // ARRANGE
+    let diff = "2c2\n< b\n---\n> B\n4a5,6\n> E\n> F\n7,8d8\n< g\n< h\n";
+
+    // ACT
+    let patch = parse_normal_diff(diff).unwrap();
+
+    // ASSERT
+    assert_eq!(patch.diffs.len(), 1);
+    let hunks = &patch.diffs[0].hunks;
+    assert_eq!(hunks.len(), 3);
+
+    assert_eq!((hunks[0].old_start, hunks[0].old_lines), (2, 1));
+    assert!(matches!(&hunks[0].lines[0], Line::Removal(s) if s == "b"));
+    assert!(matches!(&hunks[0].lines[1], Line::Addition(s) if s == "B"));
+
+    assert_eq!((hunks[1].old_start, hunks[1].old_lines), (4, 0));
+    assert_eq!((hunks[1].new_start, hunks[1].new_lines), (5, 2));
+
+    assert_eq!((hunks[2].old_start, hunks[2].old_lines), (7, 2));
+    assert_eq!(hunks[2].new_lines, 0);
+}
+
+#[test]
+fn test_normal_diff_splits_files_on_diff_command_lines() {
+    let diff =
+        "diff -r old/a.txt new/a.txt\n1c1\n< x\n---\n> y\ndiff -r old/b.txt new/b.txt\n1d0\n< z\n";
+
+    let patch = parse_normal_diff(diff).unwrap();
+
+    assert_eq!(patch.diffs.len(), 2);
+    assert_eq!(patch.diffs[0].old_file, "old/a.txt");
+    assert_eq!(patch.diffs[0].new_file, "new/a.txt");
+    assert_eq!(patch.diffs[1].new_file, "new/b.txt");
+}
+
+#[test]
+fn test_normal_diff_removals_anchor_after_drift() {
+    // ARRANGE: Two lines were added at the top since the diff was made.
+    let original_lines = to_lines("new 1\nnew 2\nalpha\nbeta\ngamma");
+    let patch = parse_normal_diff("2c2\n< beta\n---\n> BETA\n").unwrap();
+    let hunk = &patch.diffs[0].hunks[0];
+
+    // ACT
+    let matches = patcher::find_strict_match(&original_lines, hunk, 0, false);
+
+    // ASSERT
+    assert_eq!(matches.len(), 1);
+    assert_eq!(matches[0].start_index, 3);
+    let result = patcher::apply_hunk(
+        &original_lines,
+        hunk,
+        matches[0].start_index,
+        matches[0].matched_length,
+    );
+    assert_eq!(result.join("\n"), "new 1\nnew 2\nalpha\nBETA\ngamma");
+}
+
+#[test]
+fn test_ed_script_reads_removals_from_original() {
+    // ARRANGE: `diff -e` emits commands bottom-up.
+    let original_lines = to_lines("a\nb\nc\nd");
+    let script = "4a\nE\n.\n2c\nB\n.\n";
+
+    // ACT
+    let patch = parse_ed_script(script, &original_lines).unwrap();
+
+    // ASSERT
+    let hunks = &patch.diffs[0].hunks;
+    assert_eq!(hunks.len(), 2);
+    assert_eq!(hunks[0].old_start, 2);
+    assert!(matches!(&hunks[0].lines[0], Line::Removal(s) if s == "b"));
+    assert!(matches!(&hunks[0].lines[1], Line::Addition(s) if s == "B"));
+    assert_eq!((hunks[1].old_start, hunks[1].old_lines), (4, 0));
+}
+
+#[test]
+fn test_ed_script_rejects_out_of_range_commands() {
+    let original_lines = to_lines("a\nb");
+    assert!(parse_ed_script("5,6d\n", &original_lines).is_err());
+    assert!(parse_ed_script("0d\n", &original_lines).is_err());
+    assert!(parse_ed_script("0c\nx\n.\n", &original_lines).is_err());
+    assert!(parse_ed_script("0a\nx\n.\n", &original_lines).is_ok());
+}
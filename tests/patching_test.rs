@@ -149,3 +149,17 @@ fn test_anchor_point_heuristic_succeeds() {
     // ASSERT (Part 2): Check the result.
     assert_eq!(result_str, expected);
 }
+
+#[test]
+fn test_deletion_match_score() {
+    // ARRANGE
+    let file = to_lines("fn a() {}\n\nfn b() {}\nfn c() {}");
+    let whole = to_lines("fn a() {}\nfn b() {}\n  fn c() {}");
+    let stale = to_lines("fn a() {}");
+
+    // ACT & ASSERT: Blank lines and indentation do not count.
+    assert_eq!(patcher::deletion_match_score(&whole, &file), 1.0);
+    assert!(patcher::deletion_match_score(&stale, &file) < 0.5);
+    assert_eq!(patcher::deletion_match(&whole, &file), (3, 3));
+    assert_eq!(patcher::deletion_match(&stale, &file), (1, 3));
+}
@@ -0,0 +1,198 @@
+use mend::error::AppError;
+use mend::parser::{self, StripLevel};
+use mend::paths;
+use std::fs;
+use std::path::PathBuf;
+
+const PARENT_DIR_DIFF: &str =
+    "--- project.orig/src/lib.rs\n+++ project/src/lib.rs\n@@ -1 +1 @@\n-old\n+new\n";
+
+fn scratch_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("mend-{name}-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("src")).unwrap();
+    fs::write(dir.join("src/lib.rs"), "old\n").unwrap();
+    dir
+}
+
+#[test]
+fn test_strip_components() {
+    assert_eq!(
+        paths::strip_components("a/b/c.rs", 0).as_deref(),
+        Some("a/b/c.rs")
+    );
+    assert_eq!(
+        paths::strip_components("a/b/c.rs", 1).as_deref(),
+        Some("b/c.rs")
+    );
+    assert_eq!(
+        paths::strip_components("./a//c.rs", 2).as_deref(),
+        Some("c.rs")
+    );
+    assert_eq!(paths::strip_components("c.rs", 1), None);
+}
+
+#[test]
+fn test_parses_with_explicit_strip_level() {
+    // ACT
+    let patch = parser::parse_patch_with_strip(PARENT_DIR_DIFF, StripLevel::Components(1)).unwrap();
+
+    // ASSERT
+    assert_eq!(patch.diffs[0].old_file, "src/lib.rs");
+    assert_eq!(patch.diffs[0].new_file, "src/lib.rs");
+}
+
+#[test]
+fn test_default_strips_dot_slash_prefix() {
+    let patch =
+        parser::parse_patch("--- ./src/lib.rs\n+++ ./src/lib.rs\n@@ -1 +1 @@\n-old\n+new\n")
+            .unwrap();
+    assert_eq!(patch.diffs[0].new_file, "src/lib.rs");
+}
+
+#[test]
+fn test_guesses_strip_level_from_files_on_disk() {
+    // ARRANGE
+    let dir = scratch_dir("guess");
+    let git_diff = "--- a/src/lib.rs\n+++ b/src/lib.rs\n@@ -1 +1 @@\n-old\n+new\n";
+
+    // ACT
+    let guessed = paths::guess_strip_level(PARENT_DIR_DIFF, &dir);
+    let git_guess = paths::guess_strip_level(git_diff, &dir);
+
+    // ASSERT
+    assert_eq!(guessed, Some(1));
+    assert_eq!(
+        git_guess, None,
+        "git paths already resolve, no guess needed"
+    );
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_prepend_directory_keeps_dev_null() {
+    assert_eq!(paths::prepend_directory("sub", "/dev/null"), "/dev/null");
+    assert_eq!(paths::prepend_directory("sub", "x.rs"), "sub/x.rs");
+}
+
+#[test]
+fn test_strip_directory_undoes_prepend() {
+    assert_eq!(
+        paths::strip_directory("proj", "proj/src/f.txt"),
+        "src/f.txt"
+    );
+    assert_eq!(
+        paths::strip_directory("proj/", "proj/src/f.txt"),
+        "src/f.txt"
+    );
+    assert_eq!(paths::strip_directory("proj", "/dev/null"), "/dev/null");
+    assert_eq!(
+        paths::strip_directory("proj", "project/f.txt"),
+        "project/f.txt"
+    );
+}
+
+#[test]
+fn test_suffix_match_len_compares_whole_components() {
+    assert_eq!(
+        paths::suffix_match_len("src/a/mod.rs", "src/a/mod.rs"),
+        Some(3)
+    );
+    assert_eq!(
+        paths::suffix_match_len("src/a/mod.rs", "./a/mod.rs"),
+        Some(2)
+    );
+    assert_eq!(
+        paths::suffix_match_len("a/mod.rs", "crate/src/a/mod.rs"),
+        Some(2)
+    );
+    assert_eq!(
+        paths::suffix_match_len("src/b/mod.rs", "src/a/mod.rs"),
+        None
+    );
+    assert_eq!(paths::suffix_match_len("src/amod.rs", "mod.rs"), None);
+}
+
+#[test]
+fn test_rejects_paths_escaping_the_root() {
+    let root = std::path::Path::new(".");
+    assert!(paths::validate_diff_path(root, None, "src/new_module.rs").is_ok());
+    assert!(paths::validate_diff_path(root, None, "../outside.rs").is_err());
+    assert!(paths::validate_diff_path(root, None, "src/../../outside.rs").is_err());
+    assert!(paths::validate_diff_path(root, None, "/etc/hosts").is_err());
+}
+
+#[test]
+fn test_directory_prefix_is_trusted_but_must_stay_in_root() {
+    let root = std::path::Path::new(".");
+    assert!(paths::validate_diff_path(root, Some("src/.."), "src/../lib.rs").is_ok());
+    assert!(paths::validate_diff_path(root, Some(".."), "../lib.rs").is_err());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_rejects_symlink_escapes() {
+    // ARRANGE: A symlink inside the project pointing outside of it.
+    let link = format!("target/mend-escape-{}", std::process::id());
+    let _ = fs::remove_file(&link);
+    std::os::unix::fs::symlink(std::env::temp_dir(), &link).unwrap();
+
+    // ACT
+    let result = paths::validate_diff_path(std::path::Path::new("."), None, &format!("{link}/x"));
+
+    // ASSERT
+    fs::remove_file(&link).unwrap();
+    assert!(result.unwrap_err().contains("outside the root"));
+}
+
+#[test]
+fn test_alternate_path_is_free() {
+    // ARRANGE
+    let dir = scratch_dir("alternate");
+    let existing = dir.join("src/lib.rs");
+    fs::write(dir.join("src/lib.new.rs"), "taken\n").unwrap();
+
+    // ACT
+    let alternate = paths::alternate_path(&existing.to_string_lossy());
+
+    // ASSERT
+    assert_eq!(alternate, dir.join("src/lib.new2.rs").to_string_lossy());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+const TWO_MOD_FILES_DIFF: &str = "--- a/src/a/mod.rs\n+++ b/src/a/mod.rs\n@@ -1 +1 @@\n-a\n+A\n--- a/src/b/mod.rs\n+++ b/src/b/mod.rs\n@@ -1 +1 @@\n-b\n+B\n";
+
+#[test]
+fn test_retain_target_keeps_the_longest_suffix_match() {
+    // ARRANGE
+    let mut patch = parser::parse_patch(TWO_MOD_FILES_DIFF).unwrap();
+
+    // ACT
+    let result = paths::retain_target(&mut patch, "crate/src/b/mod.rs");
+
+    // ASSERT
+    assert!(result.is_ok());
+    assert_eq!(patch.diffs.len(), 1);
+    assert_eq!(patch.diffs[0].new_file, "src/b/mod.rs");
+}
+
+#[test]
+fn test_retain_target_reports_ambiguous_and_missing_targets() {
+    // ARRANGE
+    let mut ambiguous = parser::parse_patch(TWO_MOD_FILES_DIFF).unwrap();
+    let mut missing = parser::parse_patch(TWO_MOD_FILES_DIFF).unwrap();
+
+    // ACT
+    let ambiguous = paths::retain_target(&mut ambiguous, "mod.rs");
+    let missing = paths::retain_target(&mut missing, "src/c/mod.rs");
+
+    // ASSERT
+    match ambiguous {
+        Err(AppError::AmbiguousTarget { target_file, paths }) => {
+            assert_eq!(target_file, "mod.rs");
+            assert_eq!(paths, vec!["src/a/mod.rs", "src/b/mod.rs"]);
+        }
+        other => panic!("Expected an ambiguous target, got {other:?}"),
+    }
+    assert!(matches!(missing, Err(AppError::NoMatchingChanges { .. })));
+}
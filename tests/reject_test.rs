@@ -0,0 +1,41 @@
+use mend::diff::{Hunk, Line};
+use mend::parser::parse_patch;
+use mend::reject::format_reject_file;
+
+#[test]
+fn test_reject_file_has_corrected_headers() {
+    // ARRANGE: A SEARCH/REPLACE-style hunk without line numbers, after a
+    // rejected hunk that adds a line.
+    let hunks = vec![
+        Hunk {
+            old_start: 3,
+            old_lines: 9,
+            lines: vec![
+                Line::Context("a".to_string()),
+                Line::Addition("new".to_string()),
+            ],
+            ..Default::default()
+        },
+        Hunk {
+            lines: vec![
+                Line::Removal("old".to_string()),
+                Line::Addition("new".to_string()),
+            ],
+            section_hints: vec!["fn main()".to_string()],
+            ..Default::default()
+        },
+    ];
+
+    // ACT
+    let rej = format_reject_file("src/x.rs", "src/x.rs", &hunks);
+
+    // ASSERT
+    assert_eq!(
+        rej,
+        "--- a/src/x.rs\n+++ b/src/x.rs\n@@ -1,1 +1,1 @@ fn main()\n-old\n+new\n@@ -3,1 +3,2 @@\n a\n+new\n"
+    );
+    let reparsed = parse_patch(&rej).unwrap();
+    assert_eq!(reparsed.diffs[0].new_file, "src/x.rs");
+    assert_eq!(reparsed.diffs[0].hunks.len(), 2);
+    assert_eq!(reparsed.diffs[0].hunks[1].new_start, 3);
+}
@@ -0,0 +1,76 @@
+use mend::error;
+use mend::report::{FileRecord, FileStatus, HunkRecord, HunkStatus, Report, WarningCode};
+
+fn file(status: FileStatus, hunks: &[HunkStatus]) -> FileRecord {
+    FileRecord {
+        path: "src/lib.rs".to_string(),
+        old_path: None,
+        status,
+        reject_file: None,
+        hunks: hunks
+            .iter()
+            .enumerate()
+            .map(|(index, &status)| HunkRecord::unplaced(index, status))
+            .collect(),
+    }
+}
+
+fn report(files: Vec<FileRecord>) -> Report {
+    Report {
+        files,
+        ..Report::default()
+    }
+}
+
+#[test]
+fn test_clean_run_succeeds() {
+    // ARRANGE
+    let report = report(vec![file(FileStatus::Modified, &[HunkStatus::Applied])]);
+
+    // ACT & ASSERT
+    assert_eq!(report.exit_code(false), error::EXIT_SUCCESS);
+    assert_eq!(report.exit_code(true), error::EXIT_SUCCESS);
+}
+
+#[test]
+fn test_skipped_hunks_and_files_are_partial() {
+    // ARRANGE
+    let skipped_hunk = report(vec![file(
+        FileStatus::Modified,
+        &[HunkStatus::Applied, HunkStatus::Skipped],
+    )]);
+    let applied_before = report(vec![file(
+        FileStatus::Modified,
+        &[HunkStatus::AlreadyApplied],
+    )]);
+    // A binary file or a declined deletion has no hunk records.
+    let skipped_file = report(vec![
+        file(FileStatus::Modified, &[HunkStatus::Applied]),
+        file(FileStatus::Skipped, &[]),
+    ]);
+
+    // ACT & ASSERT
+    assert_eq!(skipped_hunk.exit_code(false), error::EXIT_PARTIAL);
+    assert_eq!(applied_before.exit_code(false), error::EXIT_PARTIAL);
+    assert_eq!(skipped_file.exit_code(false), error::EXIT_PARTIAL);
+}
+
+#[test]
+fn test_warnings_fail_only_when_asked() {
+    // ARRANGE
+    let mut warned = report(vec![file(FileStatus::Modified, &[HunkStatus::Applied])]);
+    warned.warn(
+        WarningCode::FuzzyMatch,
+        "Applied with fuzzy matching.".to_string(),
+    );
+    let mut skipped = report(vec![file(FileStatus::Skipped, &[])]);
+    skipped.warn(
+        WarningCode::SkippedBinary,
+        "Skipped binary file.".to_string(),
+    );
+
+    // ACT & ASSERT: Skipping outranks warnings.
+    assert_eq!(warned.exit_code(false), error::EXIT_SUCCESS);
+    assert_eq!(warned.exit_code(true), error::EXIT_WARNINGS);
+    assert_eq!(skipped.exit_code(true), error::EXIT_PARTIAL);
+}
@@ -0,0 +1,131 @@
+use mend::diff::Line;
+use mend::parser::{DiffFormat, detect_format};
+use mend::patcher;
+use mend::search_replace::parse_search_replace;
+
+fn to_lines(s: &str) -> Vec<String> {
+    s.lines().map(String::from).collect()
+}
+
+const AIDER_ANSWER: &str = r#"I'll rename the greeting.
+
+src/greet.py
+```python
+<<<<<<< SEARCH
+def greet(name):
+    return "Hello " + name
+=======
+def greet(name):
+    return f"Hi {name}"
+>>>>>>> REPLACE
+```
+
+And add a helper at the end:
+
+```python
+<<<<<<< SEARCH
+def farewell(name):
+=======
+def farewell(name):
+    """Say goodbye."""
+>>>>>>> REPLACE
+```
+
+**src/new_module.py**
+```python
+<<<<<<< SEARCH
+=======
+VALUE = 1
+>>>>>>> REPLACE
+```
+"#;
+
+#[test]
+fn test_detects_search_replace_format() {
+    assert_eq!(detect_format(AIDER_ANSWER), DiffFormat::SearchReplace);
+}
+
+#[test]
+fn test_groups_blocks_by_file() {
+    // ACT
+    let patch = parse_search_replace(AIDER_ANSWER).unwrap();
+
+    // ASSERT
+    assert_eq!(patch.diffs.len(), 2);
+
+    let edited = &patch.diffs[0];
+    assert_eq!(edited.old_file, "src/greet.py");
+    assert_eq!(edited.new_file, "src/greet.py");
+    assert_eq!(edited.hunks.len(), 2);
+    assert_eq!(edited.hunks[0].old_lines, 2);
+    assert_eq!(edited.hunks[0].new_lines, 2);
+    assert!(matches!(&edited.hunks[0].lines[0], Line::Removal(s) if s == "def greet(name):"));
+    assert!(matches!(&edited.hunks[0].lines[2], Line::Addition(s) if s == "def greet(name):"));
+
+    let created = &patch.diffs[1];
+    assert_eq!(created.old_file, "/dev/null");
+    assert_eq!(created.new_file, "src/new_module.py");
+    assert!(matches!(&created.hunks[0].lines[0], Line::Addition(s) if s == "VALUE = 1"));
+}
+
+#[test]
+fn test_prose_line_is_not_a_filename() {
+    // ARRANGE
+    let input = "src/greet.py
+```python
+<<<<<<< SEARCH
+a = 1
+=======
+a = 2
+>>>>>>> REPLACE
+```
+
+Then:
+```python
+<<<<<<< SEARCH
+b = 1
+=======
+b = 2
+>>>>>>> REPLACE
+```";
+
+    // ACT
+    let patch = parse_search_replace(input).unwrap();
+
+    // ASSERT: Both blocks edit the same file.
+    assert_eq!(patch.diffs.len(), 1);
+    assert_eq!(patch.diffs[0].new_file, "src/greet.py");
+    assert_eq!(patch.diffs[0].hunks.len(), 2);
+}
+
+#[test]
+fn test_unterminated_block_is_an_error() {
+    let input = "a.py\n<<<<<<< SEARCH\nold\n=======\nnew\n";
+    assert!(parse_search_replace(input).is_err());
+}
+
+#[test]
+fn test_search_block_matches_fuzzily() {
+    // ARRANGE: The file is indented differently than the SEARCH text.
+    let source = to_lines("class A:\n        def run(self):\n            pass");
+    let patch =
+        parse_search_replace("a.py\n<<<<<<< SEARCH\ndef run(self):\n    pass\n=======\ndef run(self):\n    return 1\n>>>>>>> REPLACE\n")
+            .unwrap();
+    let hunk = &patch.diffs[0].hunks[0];
+
+    // ACT
+    let (clean_source_map, clean_index_map) = patcher::build_lookup_tables(&source);
+    let matches = patcher::find_hunk_location(
+        &source,
+        &clean_source_map,
+        &clean_index_map,
+        hunk,
+        2,
+        false,
+        0.7,
+    );
+
+    // ASSERT
+    assert_eq!(matches.len(), 1);
+    assert_eq!(matches[0].start_index, 1);
+}
@@ -0,0 +1,108 @@
+use mend::diff::{FileDiff, Hunk, Line};
+use mend::parser::parse_patch;
+
+#[test]
+fn test_round_trips_git_diff() {
+    // ARRANGE
+    let diff = "\
+diff --git a/src/lib.rs b/src/lib.rs
+index 3b18e51..a042389 100644
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -1,3 +1,4 @@ mod a
+ mod a;
++mod b;
+ mod c;
+ mod d;
+@@ -10,2 +11,2 @@ fn main()
+ fn main() {
+-}
+\\ No newline at end of file
++}
+diff --git a/old.txt b/new.txt
+rename from old.txt
+rename to new.txt
+diff --git a/run.sh b/run.sh
+new file mode 100755
+index 0000000..e69de29
+--- /dev/null
++++ b/run.sh
+@@ -0,0 +1,1 @@
++echo hi
+\\ No newline at end of file
+diff --git a/gone.txt b/gone.txt
+deleted file mode 100644
+--- a/gone.txt
++++ /dev/null
+@@ -1,1 +0,0 @@
+-bye
+";
+
+    // ACT
+    let patch = parse_patch(diff).unwrap();
+
+    // ASSERT
+    assert_eq!(patch.to_string(), diff);
+}
+
+#[test]
+fn test_recomputes_hunk_counts() {
+    // ARRANGE: The counts and the new start are wrong, as LLMs often write.
+    let diff = "--- a/f.txt\n+++ b/f.txt\n@@ -2,7 +2,1 @@\n a\n-b\n+B\n+C\n c\n@@ -9 +9 @@\n-x\n";
+
+    // ACT
+    let patch = parse_patch(diff).unwrap();
+
+    // ASSERT
+    assert_eq!(
+        patch.to_string(),
+        "diff --git a/f.txt b/f.txt\n--- a/f.txt\n+++ b/f.txt\n@@ -2,3 +2,4 @@\n a\n-b\n+B\n+C\n c\n@@ -9,1 +9,0 @@\n-x\n"
+    );
+}
+
+#[test]
+fn test_quotes_unusual_paths() {
+    // ARRANGE
+    let file_diff = FileDiff {
+        old_file: "café.txt".to_string(),
+        new_file: "café.txt".to_string(),
+        hunks: vec![Hunk {
+            old_start: 1,
+            lines: vec![
+                Line::Removal("a".to_string()),
+                Line::Addition("b".to_string()),
+            ],
+            ..Default::default()
+        }],
+        ..Default::default()
+    };
+
+    // ACT
+    let text = file_diff.to_string();
+    let reparsed = parse_patch(&text).unwrap();
+
+    // ASSERT
+    assert!(text.starts_with("diff --git \"a/caf\\303\\251.txt\" \"b/caf\\303\\251.txt\"\n"));
+    assert_eq!(reparsed.diffs[0].new_file, "café.txt");
+}
+
+#[test]
+fn test_formats_single_hunk() {
+    // ARRANGE
+    let hunk = Hunk {
+        old_start: 4,
+        new_start: 5,
+        lines: vec![
+            Line::Context("a".to_string()),
+            Line::Addition("b".to_string()),
+        ],
+        section_hints: vec!["fn main()".to_string()],
+        ..Default::default()
+    };
+
+    // ACT
+    let text = hunk.to_string();
+
+    // ASSERT
+    assert_eq!(text, "@@ -4,1 +5,2 @@ fn main()\n a\n+b\n");
+}
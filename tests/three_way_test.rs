@@ -0,0 +1,132 @@
+use mend::parser::parse_patch;
+use mend::three_way;
+use std::fs;
+use std::process::Command;
+
+fn to_lines(s: &str) -> Vec<String> {
+    s.lines().map(String::from).collect()
+}
+
+#[test]
+fn test_parses_blob_ids_from_index_line() {
+    // ARRANGE
+    let diff = "diff --git a/a.txt b/a.txt\nindex 3b18e51..a042389 100644\n--- a/a.txt\n+++ b/a.txt\n@@ -1 +1 @@\n-one\n+two\n";
+
+    // ACT
+    let patch = parse_patch(diff).unwrap();
+
+    // ASSERT
+    let file_diff = &patch.diffs[0];
+    assert_eq!(file_diff.old_blob.as_deref(), Some("3b18e51"));
+    assert_eq!(file_diff.new_blob.as_deref(), Some("a042389"));
+}
+
+#[test]
+fn test_reads_blob_from_given_repository() {
+    // ARRANGE: A blob stored only in a separate repository.
+    let repo = std::env::temp_dir().join(format!("mend-blob-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&repo);
+    fs::create_dir_all(&repo).unwrap();
+    let git = |args: &[&str]| {
+        let output = Command::new("git")
+            .args(args)
+            .current_dir(&repo)
+            .output()
+            .unwrap();
+        String::from_utf8(output.stdout).unwrap().trim().to_string()
+    };
+    git(&["init", "-q"]);
+    fs::write(repo.join("f.txt"), "only in the other repository\n").unwrap();
+    let blob_id = git(&["hash-object", "-w", "f.txt"]);
+
+    // ACT
+    let found = three_way::read_git_blob(&repo, &blob_id);
+    let missing = three_way::read_git_blob(&std::env::temp_dir(), &blob_id);
+
+    // ASSERT
+    assert_eq!(
+        found,
+        Some(vec!["only in the other repository".to_string()])
+    );
+    assert_eq!(missing, None);
+    fs::remove_dir_all(&repo).unwrap();
+}
+
+#[test]
+fn test_applies_diff_to_its_base() {
+    // ARRANGE
+    let base = to_lines("a\nb\nc\nd\ne");
+    let diff = "--- a/f\n+++ b/f\n@@ -2,1 +2,2 @@\n b\n+b2\n@@ -4,1 +5,1 @@\n-d\n+D\n";
+    let patch = parse_patch(diff).unwrap();
+
+    // ACT
+    let result = three_way::apply_to_base(&base, &patch.diffs[0]);
+
+    // ASSERT
+    assert_eq!(result, Some(to_lines("a\nb\nb2\nc\nD\ne")));
+}
+
+#[test]
+fn test_apply_to_base_rejects_other_content() {
+    // ARRANGE
+    let base = to_lines("a\nx\nc");
+    let patch = parse_patch("--- a/f\n+++ b/f\n@@ -2,1 +2,1 @@\n-b\n+B\n").unwrap();
+
+    // ACT
+    let result = three_way::apply_to_base(&base, &patch.diffs[0]);
+
+    // ASSERT
+    assert_eq!(result, None);
+}
+
+#[test]
+fn test_merges_changes_to_different_regions() {
+    // ARRANGE
+    let base = to_lines("one\ntwo\nthree\nfour\nfive");
+    let ours = to_lines("zero\none\ntwo\nthree\nfour\nfive");
+    let theirs = to_lines("one\ntwo\nthree\nFOUR\nfive");
+
+    // ACT
+    let merged = three_way::merge3(&base, &ours, &theirs);
+
+    // ASSERT
+    assert_eq!(merged.conflicts, 0);
+    assert_eq!(merged.lines, to_lines("zero\none\ntwo\nthree\nFOUR\nfive"));
+}
+
+#[test]
+fn test_reports_conflicting_changes() {
+    // ARRANGE
+    let base = to_lines("one\ntwo\nthree");
+    let ours = to_lines("one\nTWO\nthree");
+    let theirs = to_lines("one\n2\nthree");
+
+    // ACT
+    let merged = three_way::merge3(&base, &ours, &theirs);
+
+    // ASSERT
+    assert_eq!(merged.conflicts, 1);
+    assert_eq!(
+        merged.lines,
+        to_lines("one\n<<<<<<< current\nTWO\n||||||| base\ntwo\n=======\n2\n>>>>>>> diff\nthree")
+    );
+}
+
+#[test]
+fn test_merges_large_files() {
+    // ARRANGE: Too large for a quadratic table of the lines.
+    let base: Vec<String> = (0..50_000).map(|i| format!("line {i}")).collect();
+    let mut ours = base.clone();
+    ours[10] = "ours".to_string();
+    let mut theirs = base.clone();
+    theirs[49_990] = "theirs".to_string();
+
+    // ACT
+    let merged = three_way::merge3(&base, &ours, &theirs);
+
+    // ASSERT
+    assert_eq!(merged.conflicts, 0);
+    assert_eq!(merged.lines.len(), base.len());
+    assert_eq!(merged.lines[10], "ours");
+    assert_eq!(merged.lines[49_990], "theirs");
+}
@@ -0,0 +1,122 @@
+use mend::unified;
+
+#[test]
+fn test_renders_change_with_context() {
+    // ARRANGE
+    let old = "1\n2\n3\n4\n5\n6\n7\n8\n9\n";
+    let new = "1\n2\n3\n4\nfive\n6\n7\n8\n9\n";
+
+    // ACT
+    let diff = unified::unified_diff("f.txt", "f.txt", old, new, 3);
+
+    // ASSERT
+    assert_eq!(
+        diff,
+        "--- a/f.txt\n+++ b/f.txt\n@@ -2,7 +2,7 @@\n 2\n 3\n 4\n-5\n+five\n 6\n 7\n 8\n"
+    );
+}
+
+#[test]
+fn test_splits_distant_changes_into_hunks() {
+    // ARRANGE
+    let old = "1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n";
+    let new = "0\n1\n2\n3\n4\n5\n6\n7\n8\n9\n";
+
+    // ACT
+    let diff = unified::unified_diff("f.txt", "f.txt", old, new, 1);
+
+    // ASSERT
+    assert_eq!(
+        diff,
+        "--- a/f.txt\n+++ b/f.txt\n@@ -1,1 +1,2 @@\n+0\n 1\n@@ -9,2 +10,1 @@\n 9\n-10\n"
+    );
+}
+
+#[test]
+fn test_identical_content_renders_nothing() {
+    // ARRANGE
+    let lines = "a\nb\n";
+
+    // ACT
+    let diff = unified::unified_diff("f.txt", "f.txt", lines, lines, 3);
+
+    // ASSERT
+    assert!(diff.is_empty());
+}
+
+#[test]
+fn test_renders_created_file() {
+    // ARRANGE
+    let new = "a\nb\n";
+
+    // ACT
+    let diff = unified::unified_diff("/dev/null", "new.txt", "", new, 3);
+
+    // ASSERT
+    assert_eq!(
+        diff,
+        "--- /dev/null\n+++ b/new.txt\n@@ -0,0 +1,2 @@\n+a\n+b\n"
+    );
+}
+
+#[test]
+fn test_colorizes_diff_lines() {
+    // ARRANGE
+    let diff = "--- a/f\n+++ b/f\n@@ -1,1 +1,1 @@\n-old\n+new\n same\n";
+
+    // ACT
+    let colored = unified::colorize(diff);
+
+    // ASSERT
+    assert_eq!(
+        colored,
+        "\x1b[1m--- a/f\x1b[0m\n\x1b[1m+++ b/f\x1b[0m\n\x1b[36m@@ -1,1 +1,1 @@\x1b[0m\n\x1b[31m-old\x1b[0m\n\x1b[32m+new\x1b[0m\n same\n"
+    );
+}
+
+#[test]
+fn test_git_diff_describes_renames() {
+    // ARRANGE
+    let old = "a\nb\n";
+    let new = "a\nc\n";
+
+    // ACT
+    let diff = unified::git_diff("x.rs", "y.rs", old, new, 3);
+
+    // ASSERT
+    assert_eq!(
+        diff,
+        "diff --git a/x.rs b/y.rs\nrename from x.rs\nrename to y.rs\n--- a/x.rs\n+++ b/y.rs\n@@ -1,2 +1,2 @@\n a\n-b\n+c\n"
+    );
+}
+
+#[test]
+fn test_git_diff_marks_deleted_files() {
+    // ARRANGE
+    let old = "a\n";
+
+    // ACT
+    let diff = unified::git_diff("gone.txt", "/dev/null", old, "", 3);
+
+    // ASSERT
+    assert_eq!(
+        diff,
+        "diff --git a/gone.txt b/gone.txt\ndeleted file mode 100644\n--- a/gone.txt\n+++ /dev/null\n@@ -1,1 +0,0 @@\n-a\n"
+    );
+}
+
+#[test]
+fn test_marks_missing_newline_at_end_of_file() {
+    // ARRANGE
+    let old = "a\nb";
+    let new = "a\nb\n";
+
+    // ACT
+    let diff = unified::unified_diff("f.txt", "f.txt", old, new, 3);
+
+    // ASSERT
+    assert_eq!(
+        diff,
+        "--- a/f.txt\n+++ b/f.txt\n@@ -1,2 +1,2 @@\n a\n-b\n\\ No newline at end of file\n+b\n"
+    );
+}
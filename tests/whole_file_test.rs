@@ -0,0 +1,154 @@
+use mend::diff::Line;
+use mend::elision::ElisionMatcher;
+use mend::parser::{DiffFormat, detect_format};
+use mend::whole_file::{build_whole_file_diff, extract_file_blocks, truncation_warnings};
+
+fn to_lines(s: &str) -> Vec<String> {
+    s.lines().map(String::from).collect()
+}
+
+#[test]
+fn test_detects_path_hints() {
+    // ARRANGE
+    let input = "Here is the new file:
+
+```rust src/lib.rs
+pub fn a() {}
+```
+
+And the script:
+
+```python
+# File: tools/run.py
+print(\"hi\")
+```
+
+```text
+no path here
+```";
+
+    // ACT
+    let blocks = extract_file_blocks(input);
+
+    // ASSERT
+    assert_eq!(detect_format(input), DiffFormat::WholeFile);
+    assert_eq!(blocks.len(), 2);
+    assert_eq!(blocks[0].path, "src/lib.rs");
+    assert_eq!(blocks[0].lines, vec!["pub fn a() {}"]);
+    assert_eq!(blocks[1].path, "tools/run.py");
+    assert_eq!(blocks[1].lines, vec!["print(\"hi\")"]);
+}
+
+#[test]
+fn test_diff_against_current_content() {
+    // ARRANGE
+    let old = to_lines("fn a() {\n    1\n}");
+    let blocks = extract_file_blocks("```rust a.rs\nfn a() {\n    2\n}\n```");
+
+    // ACT
+    let file_diff = build_whole_file_diff(&blocks[0], Some(&old));
+
+    // ASSERT
+    assert_eq!(file_diff.old_file, "a.rs");
+    let lines = &file_diff.hunks[0].lines;
+    assert!(matches!(&lines[0], Line::Context(s) if s == "fn a() {"));
+    assert!(matches!(&lines[1], Line::Removal(s) if s == "    1"));
+    assert!(matches!(&lines[2], Line::Addition(s) if s == "    2"));
+}
+
+#[test]
+fn test_diff_of_large_block() {
+    // ARRANGE: A 20,000-line file with every tenth line changed.
+    let old: Vec<String> = (0..20_000).map(|i| format!("line {i}")).collect();
+    let new: Vec<String> = (0..20_000)
+        .map(|i| {
+            if i % 10 == 0 {
+                format!("changed {i}")
+            } else {
+                format!("line {i}")
+            }
+        })
+        .collect();
+    let blocks = extract_file_blocks(&format!("```text big.txt\n{}\n```", new.join("\n")));
+
+    // ACT
+    let file_diff = build_whole_file_diff(&blocks[0], Some(&old));
+
+    // ASSERT
+    let lines = &file_diff.hunks[0].lines;
+    let count = |f: fn(&Line) -> bool| lines.iter().filter(|l| f(l)).count();
+    assert_eq!(count(|l| matches!(l, Line::Removal(_))), 2_000);
+    assert_eq!(count(|l| matches!(l, Line::Addition(_))), 2_000);
+    assert_eq!(count(|l| matches!(l, Line::Context(_))), 18_000);
+}
+
+#[test]
+fn test_missing_file_becomes_a_creation() {
+    let blocks = extract_file_blocks("```rust new.rs\nfn a() {}\n```");
+    let file_diff = build_whole_file_diff(&blocks[0], None);
+    assert_eq!(file_diff.old_file, "/dev/null");
+    assert_eq!(file_diff.new_file, "new.rs");
+}
+
+#[test]
+fn test_truncation_heuristics() {
+    let elision = ElisionMatcher::default();
+    let old = to_lines(&"fn x() {}\n".repeat(30));
+
+    let complete = to_lines(&"fn x() {}\n".repeat(29));
+    assert!(truncation_warnings("x.rs", Some(&old), &complete, &elision).is_empty());
+
+    let open_brace = to_lines("fn x() {\n    let a = 1;");
+    assert!(!truncation_warnings("x.rs", None, &open_brace, &elision).is_empty());
+
+    let elided = to_lines("fn x() {}\n// ... existing code ...");
+    assert!(!truncation_warnings("x.rs", None, &elided, &elision).is_empty());
+
+    let short = to_lines("fn x() {}");
+    let warnings = truncation_warnings("x.rs", Some(&old), &short, &elision);
+    assert_eq!(warnings.len(), 1);
+}
+
+#[test]
+fn test_bracket_balance_follows_the_language() {
+    let elision = ElisionMatcher::default();
+
+    // Brackets inside `'` strings and `#` comments do not count in Python.
+    let python = to_lines("def f():\n    s = '(['  # close it: }\n    return s // 2");
+    assert!(truncation_warnings("f.py", None, &python, &elision).is_empty());
+
+    // Rust lifetimes are not quotes, but character literals are.
+    let rust = to_lines("fn f<'a>(x: &'a str) -> char {\n    '{'\n}");
+    assert!(truncation_warnings("f.rs", None, &rust, &elision).is_empty());
+}
+
+#[test]
+fn test_comments_that_are_not_paths() {
+    // ARRANGE: Unlabeled first-line comments need a directory and a known
+    // extension to name a file.
+    let input = "```python
+# e.g. call it twice
+print(1)
+```
+
+```js
+// v1.0
+run();
+```
+
+```ruby
+# lib/tasks/run.rb
+puts 1
+```";
+
+    // ACT
+    let blocks = extract_file_blocks(input);
+
+    // ASSERT
+    assert_eq!(blocks.len(), 1);
+    assert_eq!(blocks[0].path, "lib/tasks/run.rb");
+    assert_ne!(
+        detect_format("```python\n# e.g. call it twice\nprint(1)\n```"),
+        DiffFormat::WholeFile
+    );
+}